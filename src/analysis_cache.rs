@@ -0,0 +1,278 @@
+// src/analysis_cache.rs - Caches complete analyses per tenant, catalog and workflow version
+
+use crate::analyze_sentence::AnalysisResult;
+use crate::metrics;
+use crate::utils::fingerprint::fingerprint;
+use crate::utils::lru::Lru;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+fn default_capacity() -> usize {
+    500
+}
+
+fn default_ttl_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnalysisCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for AnalysisCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: default_capacity(),
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+/// What an analysis depends on besides the sentence. A new catalog, workflow
+/// or prompt version changes the key, so results computed before are not served.
+pub struct AnalysisKey<'a> {
+    pub email: &'a str,
+    pub catalog_fingerprint: &'a str,
+    pub workflow: &'a str,
+    pub prompt_versions: &'a str,
+    pub sentence: &'a str,
+}
+
+impl AnalysisKey<'_> {
+    /// SHA-256 of the key, so requests of different tenants never share a result
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&[
+            &self.email.trim().to_lowercase(),
+            self.catalog_fingerprint,
+            self.workflow,
+            self.prompt_versions,
+            &normalize_sentence(self.sentence),
+        ])
+    }
+}
+
+/// Repeats of a sentence differing only in whitespace share a result. Case
+/// is kept: the extracted values and their spans come from the sentence.
+pub fn normalize_sentence(sentence: &str) -> String {
+    sentence.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Result of a run shared with the requests that waited for it; errors are
+// shared as text since boxed errors can't be cloned
+type SharedRun = Arc<OnceCell<Result<AnalysisResult, String>>>;
+
+/// Completed analyses, and the runs in progress that identical requests join
+/// instead of starting their own pipeline
+pub struct AnalysisCache {
+    config: AnalysisCacheConfig,
+    results: Mutex<Lru<(AnalysisResult, Instant)>>,
+    in_flight: Mutex<HashMap<String, SharedRun>>,
+}
+
+impl AnalysisCache {
+    pub fn new(config: AnalysisCacheConfig) -> Self {
+        Self {
+            config,
+            results: Mutex::new(Lru::default()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cache of the process, created with the configuration of its first use
+    pub fn shared(config: AnalysisCacheConfig) -> &'static AnalysisCache {
+        static CACHE: OnceLock<AnalysisCache> = OnceLock::new();
+        CACHE.get_or_init(|| AnalysisCache::new(config))
+    }
+
+    fn lookup(&self, key: &str) -> Option<AnalysisResult> {
+        let mut results = self.results.lock().unwrap();
+        let (result, created_at) = results.get(key)?;
+        if created_at.elapsed() >= Duration::from_secs(self.config.ttl_secs) {
+            results.remove(key);
+            return None;
+        }
+        Some(result)
+    }
+
+    /// Returns the cached result of `key`, joins the run in progress for it,
+    /// or runs `analyze` and caches its result. Results not produced by this
+    /// call's own run are flagged `cached`.
+    pub async fn get_or_analyze<F, Fut>(
+        &self,
+        key: &str,
+        analyze: F,
+    ) -> Result<AnalysisResult, Box<dyn Error + Send + Sync>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<AnalysisResult, Box<dyn Error + Send + Sync>>>,
+    {
+        if let Some(mut result) = self.lookup(key) {
+            debug!("Analysis cache hit for {}", key);
+            metrics::increment("analysis_cache_hits", 1);
+            result.cached = true;
+            return Ok(result);
+        }
+
+        let run = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+
+        let mut leader = false;
+        let shared = run
+            .get_or_init(|| async {
+                leader = true;
+                metrics::increment("analysis_cache_misses", 1);
+                let result = analyze().await.map_err(|e| e.to_string());
+                if let Ok(result) = &result {
+                    self.results.lock().unwrap().insert(
+                        key.to_string(),
+                        (result.clone(), Instant::now()),
+                        self.config.capacity,
+                    );
+                }
+                // Requests arriving from now on find the result, or run again after an error
+                self.in_flight.lock().unwrap().remove(key);
+                result
+            })
+            .await
+            .clone();
+
+        if !leader {
+            debug!("Joined the analysis in progress for {}", key);
+            metrics::increment("analysis_cache_coalesced", 1);
+        }
+        let mut result = shared.map_err(|e| -> Box<dyn Error + Send + Sync> { e.into() })?;
+        result.cached = !leader;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn result(endpoint_id: &str) -> AnalysisResult {
+        AnalysisResult {
+            json_output: json!({}),
+            endpoint_id: endpoint_id.to_string(),
+            endpoint_description: String::new(),
+            parameters: vec![],
+            trace: vec![],
+            escalations: 0,
+            cached: false,
+            usage: Default::default(),
+        }
+    }
+
+    fn key(sentence: &str) -> String {
+        AnalysisKey {
+            email: "user@example.com",
+            catalog_fingerprint: "catalog",
+            workflow: "sentence_first",
+            prompt_versions: "prompts",
+            sentence,
+        }
+        .fingerprint()
+    }
+
+    #[test]
+    fn test_key_normalizes_sentence() {
+        assert_eq!(
+            key("Send the report to Alice"),
+            key("  Send the   report to Alice ")
+        );
+        assert_ne!(
+            key("Create ticket titled Foo"),
+            key("create ticket titled foo")
+        );
+        assert_ne!(
+            key("Send the report to Alice"),
+            key("Send the report to Bob")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_and_coalesced_results() {
+        let cache = Arc::new(AnalysisCache::new(AnalysisCacheConfig {
+            enabled: true,
+            ..Default::default()
+        }));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let requests = (0..5).map(|_| {
+            let cache = cache.clone();
+            let runs = runs.clone();
+            tokio::spawn(async move {
+                cache
+                    .get_or_analyze("key", || async {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(result("send_email"))
+                    })
+                    .await
+                    .unwrap()
+            })
+        });
+        let results = futures::future::join_all(requests).await;
+
+        // One pipeline ran, the other requests waited for its result
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let cached = results
+            .into_iter()
+            .map(|result| result.unwrap().cached)
+            .filter(|cached| *cached)
+            .count();
+        assert_eq!(cached, 4);
+
+        let repeat = cache
+            .get_or_analyze("key", || async { Err("not expected to run".into()) })
+            .await
+            .unwrap();
+        assert!(repeat.cached);
+        assert_eq!(repeat.endpoint_id, "send_email");
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_expired_results_are_not_served() {
+        let cache = AnalysisCache::new(AnalysisCacheConfig {
+            enabled: true,
+            ttl_secs: 0,
+            ..Default::default()
+        });
+
+        let failed = cache
+            .get_or_analyze("key", || async { Err("service unavailable".into()) })
+            .await;
+        assert_eq!(failed.unwrap_err().to_string(), "service unavailable");
+
+        let first = cache
+            .get_or_analyze("key", || async { Ok(result("send_email")) })
+            .await
+            .unwrap();
+        assert!(!first.cached);
+        let second = cache
+            .get_or_analyze("key", || async { Ok(result("send_sms")) })
+            .await
+            .unwrap();
+        assert_eq!(second.endpoint_id, "send_sms");
+    }
+}
@@ -1,36 +1,140 @@
+use crate::analysis_cache::{AnalysisCache, AnalysisKey};
 use crate::endpoint_client::endpoint;
 use crate::endpoint_client::{check_endpoint_service_health, convert_remote_endpoints};
 // use crate::models::config::is_debug_mode_with_local_endpoints;
 use crate::endpoint_client::get_default_endpoints;
-use crate::models::config::load_models_config;
-use crate::models::providers::ModelProvider;
+use crate::models::config::{
+    load_analysis_cache_config, load_models_config, load_price_table, load_workflow_settings,
+    WorkflowSettings,
+};
+use crate::models::provenance::ValueSource;
+use crate::models::providers::error::ProviderError;
+use crate::models::providers::ModelConfig;
+use crate::models::providers::ProviderRegistry;
 use crate::models::ConfigFile;
+use crate::models::Endpoint;
 use crate::models::EndpointParameter;
+use crate::prompts::PromptManager;
+use crate::usage::{PriceTable, RunUsage};
 use crate::utils::email::validate_email;
-use crate::workflow::find_closest_endpoint::find_closest_endpoint;
-use crate::workflow::match_fields::match_fields_semantic;
+use crate::utils::fingerprint::fingerprint;
+use crate::workflow::cascade::{assess_parameters, Cascade, EscalationReason};
+use crate::workflow::extract_parameters::extract_parameters;
+use crate::workflow::find_closest_endpoint::{
+    ask_closest_endpoint, find_closest_endpoint, match_endpoint_answer,
+};
+use crate::workflow::grounding::{ground_value, GroundingConfig, GroundingPolicy};
+use crate::workflow::match_fields::{match_fields_semantic, value_to_text};
+use crate::workflow::select_endpoint_tool::select_endpoint_tool;
 use crate::workflow::sentence_to_json::sentence_to_json;
+use crate::workflow::trace::{self, TraceEntry, TraceEvent};
 use crate::workflow::WorkflowEngine;
 use crate::workflow::WorkflowStep;
-use crate::workflow::{WorkflowConfig, WorkflowContext};
+use crate::workflow::{
+    RetryConfig, StepConfig, StepRouting, WorkflowConfig, WorkflowContext, WorkflowMode,
+};
 use serde_json::Value;
 use std::error::Error;
-use tracing::{debug, error, info};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
+#[derive(Debug, Clone)]
 pub struct AnalysisResult {
     pub json_output: Value,
     pub endpoint_id: String,
     pub endpoint_description: String,
     pub parameters: Vec<EndpointParameter>,
+    /// Steps that ran and which providers answered them
+    pub trace: Vec<TraceEntry>,
+    /// Number of steps that escalated to their stronger model
+    pub escalations: u32,
+    /// Served from the analysis cache; `trace` and `usage` are then the ones of the original run
+    pub cached: bool,
+    /// Tokens and cost of the model calls
+    pub usage: RunUsage,
 }
 
 use async_trait::async_trait;
 use std::sync::Arc;
 
+/// Fetches the endpoint catalog of `email` from the remote endpoint service
+pub async fn load_catalog(
+    api_url: &str,
+    email: &str,
+) -> Result<ConfigFile, Box<dyn Error + Send + Sync>> {
+    // First verify the service is available
+    match check_endpoint_service_health(api_url).await {
+        Ok(true) => {
+            info!("Remote endpoint service is available, fetching endpoints");
+
+            // Use the new get_default_endpoints function
+            match get_default_endpoints(api_url, email).await {
+                Ok(remote_endpoints) => {
+                    // Convert and store endpoints
+                    let endpoints = convert_remote_endpoints(
+                        // We'll need to wrap endpoints in an ApiGroup to use the converter
+                        vec![endpoint::ApiGroup {
+                            id: "default".to_string(),
+                            name: "Default Group".to_string(),
+                            description: "Default API Group".to_string(),
+                            base: "".to_string(),
+                            endpoints: remote_endpoints,
+                        }],
+                    );
+
+                    info!("Successfully loaded {} endpoints", endpoints.len());
+                    Ok(ConfigFile { endpoints })
+                }
+                Err(e) => {
+                    error!("Failed to fetch endpoints: {}", e);
+                    Err(e)
+                }
+            }
+        }
+        Ok(false) | Err(_) => Err("Remote endpoint service is unavailable".into()),
+    }
+}
+
 // Step 2: Define each workflow step
 pub struct ConfigurationLoadingStep {
     pub api_url: Option<String>,
     pub email: String,
+    /// Longest wait for the endpoint service, from the step's `timeout_secs`
+    pub timeout: Option<Duration>,
+}
+
+impl ConfigurationLoadingStep {
+    async fn fetch_catalog(&self) -> Result<ConfigFile, Box<dyn Error + Send + Sync>> {
+        let api_url = self.api_url.as_deref().ok_or("No API URL provided")?;
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, load_catalog(api_url, &self.email))
+                .await
+                .map_err(|_| format!("The endpoint service didn't answer within {:?}", timeout))?,
+            None => load_catalog(api_url, &self.email).await,
+        }
+    }
+
+    /// Fetches the catalog outside the workflow, with the retries the engine
+    /// gives the step
+    async fn fetch_catalog_with_retry(
+        &self,
+        retry: Option<&RetryConfig>,
+    ) -> Result<ConfigFile, Box<dyn Error + Send + Sync>> {
+        let mut attempts = 0;
+        loop {
+            match self.fetch_catalog().await {
+                Ok(catalog) => return Ok(catalog),
+                Err(e) => {
+                    attempts += 1;
+                    let Some(retry) = retry.filter(|retry| attempts < retry.max_attempts) else {
+                        return Err(e);
+                    };
+                    warn!("Fetching the catalog failed, retrying: {}", e);
+                    tokio::time::sleep(Duration::from_millis(retry.delay_ms)).await;
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -56,45 +160,10 @@ impl WorkflowStep for ConfigurationLoadingStep {
 
         // Set email in context
         context.email = Some(self.email.clone());
-        // Ensure API URL is provided
-        let api_url = self.api_url.as_ref().ok_or("No API URL provided")?;
-
-        // First verify the service is available
-        match check_endpoint_service_health(api_url).await {
-            Ok(true) => {
-                info!("Remote endpoint service is available, fetching endpoints");
-
-                // Use the new get_default_endpoints function
-                match get_default_endpoints(api_url, &self.email).await {
-                    Ok(remote_endpoints) => {
-                        // Convert and store endpoints
-                        let endpoints = convert_remote_endpoints(
-                            // We'll need to wrap endpoints in an ApiGroup to use the converter
-                            vec![endpoint::ApiGroup {
-                                id: "default".to_string(),
-                                name: "Default Group".to_string(),
-                                description: "Default API Group".to_string(),
-                                base: "".to_string(),
-                                endpoints: remote_endpoints,
-                            }],
-                        );
-
-                        let endpoints_len = endpoints.len();
-
-                        let config = ConfigFile { endpoints };
-                        context.endpoints_config = Some(config);
-
-                        info!("Successfully loaded {} endpoints", endpoints_len);
-                    }
-                    Err(e) => {
-                        error!("Failed to fetch endpoints: {}", e);
-                        return Err(e);
-                    }
-                }
-            }
-            Ok(false) | Err(_) => {
-                return Err("Remote endpoint service is unavailable".into());
-            }
+
+        // The analysis cache loads the catalog before the workflow starts
+        if context.endpoints_config.is_none() {
+            context.endpoints_config = Some(self.fetch_catalog().await?);
         }
 
         // Load model configurations
@@ -110,7 +179,9 @@ impl WorkflowStep for ConfigurationLoadingStep {
 }
 
 // Step 2.2: JSON Generation Step
-pub struct JsonGenerationStep;
+pub struct JsonGenerationStep {
+    pub routing: StepRouting,
+}
 
 #[async_trait]
 impl WorkflowStep for JsonGenerationStep {
@@ -120,7 +191,9 @@ impl WorkflowStep for JsonGenerationStep {
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         info!("Generating JSON from sentence");
 
-        let json_result = sentence_to_json(&context.sentence, context.provider.clone()).await?;
+        let provider = context.provider_for(&self.routing)?;
+        let model_config = context.model_config_for(&self.routing)?;
+        let json_result = sentence_to_json(&context.sentence, provider, &model_config).await?;
         context.json_output = Some(json_result);
 
         debug!("JSON generation successful");
@@ -133,7 +206,11 @@ impl WorkflowStep for JsonGenerationStep {
 }
 
 // Step 2.3: Endpoint Matching Step
-pub struct EndpointMatchingStep;
+pub struct EndpointMatchingStep {
+    pub routing: StepRouting,
+    /// Stronger model tried when the cheap one's answer is ambiguous or unusable
+    pub cascade: Option<Cascade>,
+}
 
 #[async_trait]
 impl WorkflowStep for EndpointMatchingStep {
@@ -148,8 +225,45 @@ impl WorkflowStep for EndpointMatchingStep {
             .as_ref()
             .ok_or("Endpoints configuration not loaded")?;
 
-        let endpoint_result =
-            find_closest_endpoint(config, &context.sentence, context.provider.clone()).await?;
+        let provider = context.provider_for(&self.routing)?;
+        let model_config = context.model_config_for(&self.routing)?;
+
+        let endpoint_match = match &self.cascade {
+            None => {
+                find_closest_endpoint(config, &context.sentence, provider, &model_config).await?
+            }
+            Some(cascade) => {
+                // Provider errors are not the model's answer: they went through
+                // failover already and don't escalate
+                let answer =
+                    ask_closest_endpoint(config, &context.sentence, provider, &model_config)
+                        .await?;
+                let result = match_endpoint_answer(config, &answer).await;
+                let reason = match &result {
+                    Err(e) => {
+                        warn!("Cheap endpoint matching gave no usable answer: {}", e);
+                        Some(EscalationReason::SchemaValidation)
+                    }
+                    Ok(m) if m.confidence < cascade.config.min_confidence => {
+                        Some(EscalationReason::LowConfidence)
+                    }
+                    Ok(_) => None,
+                };
+
+                match reason {
+                    None => result?,
+                    Some(reason) => {
+                        record_escalation(&self.routing, cascade, reason);
+                        let provider = context.provider_for(&cascade.routing)?;
+                        let model_config = context.model_config_for(&cascade.routing)?;
+                        find_closest_endpoint(config, &context.sentence, provider, &model_config)
+                            .await?
+                    }
+                }
+            }
+        };
+
+        let endpoint_result = endpoint_match.endpoint;
         context.endpoint_id = Some(endpoint_result.id.clone());
         context.endpoint_description = Some(endpoint_result.description.clone());
         context.matched_endpoint = Some(endpoint_result);
@@ -163,8 +277,182 @@ impl WorkflowStep for EndpointMatchingStep {
     }
 }
 
+// Reports that a cascading step is handing over to its stronger model
+fn record_escalation(routing: &StepRouting, cascade: &Cascade, reason: EscalationReason) {
+    info!(
+        "Escalating from {} to {} ({:?})",
+        routing.model, cascade.routing.model, reason
+    );
+    trace::record(TraceEvent::Escalated {
+        from_model: routing.model.clone(),
+        to_model: cascade.routing.model.clone(),
+        reason,
+    });
+}
+
+// Step 2.3b: Schema-guided Parameter Extraction Step (endpoint-first mode)
+pub struct ParameterExtractionStep {
+    pub routing: StepRouting,
+}
+
+#[async_trait]
+impl WorkflowStep for ParameterExtractionStep {
+    async fn execute(
+        &self,
+        context: &mut crate::workflow::context::WorkflowContext,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        info!("Extracting parameters of the matched endpoint");
+
+        let endpoint = context
+            .matched_endpoint
+            .as_ref()
+            .ok_or("Matched endpoint not available")?;
+
+        let provider = context.provider_for(&self.routing)?;
+        let model_config = context.model_config_for(&self.routing)?;
+        let json_result =
+            extract_parameters(&context.sentence, endpoint, provider, &model_config).await?;
+        context.json_output = Some(json_result);
+        context.schema_extraction = true;
+
+        debug!("Parameter extraction successful");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "parameter_extraction"
+    }
+}
+
+// Step 2.3c: Tool Selection Step (tool-calling mode) - the endpoint is called
+// as a tool, falling back to matching and extraction prompts
+pub struct ToolSelectionStep {
+    pub routing: StepRouting,
+}
+
+#[async_trait]
+impl WorkflowStep for ToolSelectionStep {
+    async fn execute(
+        &self,
+        context: &mut crate::workflow::context::WorkflowContext,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        info!("Selecting endpoint through tool calling");
+
+        let config = context
+            .endpoints_config
+            .as_ref()
+            .ok_or("Endpoints configuration not loaded")?;
+
+        let provider = context.provider_for(&self.routing)?;
+        let model_config = context.model_config_for(&self.routing)?;
+        let selection =
+            match select_endpoint_tool(config, &context.sentence, provider.clone(), &model_config)
+                .await
+            {
+                Ok(Some(selection)) => Some(selection),
+                Ok(None) => {
+                    record_tool_fallback("no endpoint tool was called".to_string());
+                    None
+                }
+                Err(e) => match e.downcast_ref::<ProviderError>() {
+                    Some(unsupported @ ProviderError::ToolsUnsupported { .. }) => {
+                        record_tool_fallback(unsupported.to_string());
+                        None
+                    }
+                    _ => return Err(e),
+                },
+            };
+
+        let (endpoint, json_output) = match selection {
+            Some(selection) => (selection.endpoint, selection.json_output),
+            None => {
+                let endpoint = find_closest_endpoint(
+                    config,
+                    &context.sentence,
+                    provider.clone(),
+                    &model_config,
+                )
+                .await?
+                .endpoint;
+                let json_output =
+                    extract_parameters(&context.sentence, &endpoint, provider, &model_config)
+                        .await?;
+                (endpoint, json_output)
+            }
+        };
+
+        context.endpoint_id = Some(endpoint.id.clone());
+        context.endpoint_description = Some(endpoint.description.clone());
+        context.matched_endpoint = Some(endpoint);
+        context.json_output = Some(json_output);
+        context.schema_extraction = true;
+
+        debug!("Tool selection successful");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "tool_selection"
+    }
+}
+
+fn record_tool_fallback(reason: String) {
+    info!("Falling back to the prompt path: {}", reason);
+    trace::record(TraceEvent::ToolCallFallback { reason });
+}
+
 // Step 2.4: Field Matching Step
-pub struct FieldMatchingStep;
+pub struct FieldMatchingStep {
+    pub routing: StepRouting,
+    /// Stronger model that extracts the parameters again when the cheap
+    /// ones are missing, mistyped, ungrounded or uncertain
+    pub cascade: Option<Cascade>,
+}
+
+// Resolves the endpoint parameters from the generated JSON
+async fn match_parameters(
+    sentence: &str,
+    json_output: &Value,
+    endpoint: &Endpoint,
+    direct_source: ValueSource,
+    provider: Arc<dyn crate::models::providers::ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<Vec<EndpointParameter>, Box<dyn Error + Send + Sync>> {
+    let semantic_results = match_fields_semantic(
+        sentence,
+        json_output,
+        endpoint,
+        direct_source,
+        provider,
+        model_config,
+    )
+    .await?;
+
+    // Convert semantic results to parameters
+    Ok(endpoint
+        .parameters
+        .iter()
+        .map(|param| {
+            let matched = semantic_results
+                .iter()
+                .find(|field| field.name == param.name);
+            let value = matched.and_then(|field| field.value.clone());
+            let provenance = matched.and_then(|field| field.provenance.clone());
+
+            EndpointParameter {
+                name: param.name.clone(),
+                description: param.description.clone(),
+                semantic_value: value.as_ref().map(value_to_text),
+                alternatives: param.alternatives.clone(),
+                required: param.required,
+                param_type: param.param_type.clone(),
+                value,
+                default: param.default.clone(),
+                provenance,
+            }
+        })
+        .collect())
+}
 
 #[async_trait]
 impl WorkflowStep for FieldMatchingStep {
@@ -183,30 +471,59 @@ impl WorkflowStep for FieldMatchingStep {
             .as_ref()
             .ok_or("Matched endpoint not available")?;
 
-        let semantic_results =
-            match_fields_semantic(json_output, endpoint, context.provider.clone()).await?;
+        let provider = context.provider_for(&self.routing)?;
+        let model_config = context.model_config_for(&self.routing)?;
+        let direct_source = if context.schema_extraction {
+            ValueSource::Extractor
+        } else {
+            ValueSource::Exact
+        };
+        let parameters = match_parameters(
+            &context.sentence,
+            json_output,
+            endpoint,
+            direct_source,
+            provider,
+            &model_config,
+        )
+        .await?;
 
-        // Convert semantic results to parameters
-        let parameters: Vec<EndpointParameter> = endpoint
-            .parameters
-            .iter()
-            .map(|param| {
-                let semantic_value = semantic_results
-                    .iter()
-                    .find(|(name, _, _)| name == &param.name)
-                    .and_then(|(_, _, value)| value.clone());
-
-                EndpointParameter {
-                    name: param.name.clone(),
-                    description: param.description.clone(),
-                    semantic_value,
-                    alternatives: param.alternatives.clone(),
-                    required: param.required,
-                }
-            })
-            .collect();
+        let escalation = self.cascade.as_ref().and_then(|cascade| {
+            assess_parameters(&context.sentence, &parameters, &cascade.config)
+                .map(|reason| (reason, cascade))
+        });
 
-        context.parameters = parameters;
+        context.parameters = match escalation {
+            None => parameters,
+            Some((reason, cascade)) => {
+                record_escalation(&self.routing, cascade, reason);
+
+                // The stronger model extracts exactly the endpoint's parameters again
+                let provider = context.provider_for(&cascade.routing)?;
+                let model_config = context.model_config_for(&cascade.routing)?;
+                let endpoint = endpoint.clone();
+                let json_result = extract_parameters(
+                    &context.sentence,
+                    &endpoint,
+                    provider.clone(),
+                    &model_config,
+                )
+                .await?;
+                let parameters = match_parameters(
+                    &context.sentence,
+                    &json_result,
+                    &endpoint,
+                    ValueSource::Extractor,
+                    provider,
+                    &model_config,
+                )
+                .await?;
+
+                context.json_output = Some(json_result);
+                context.schema_extraction = true;
+                parameters
+            }
+        };
 
         debug!("Field matching completed");
         Ok(())
@@ -217,6 +534,68 @@ impl WorkflowStep for FieldMatchingStep {
     }
 }
 
+// Step 2.5: Grounding Check Step - flags values that can't be traced back to the sentence
+pub struct GroundingCheckStep {
+    pub config: GroundingConfig,
+}
+
+#[async_trait]
+impl WorkflowStep for GroundingCheckStep {
+    async fn execute(
+        &self,
+        context: &mut crate::workflow::context::WorkflowContext,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        info!("Checking parameter values against the sentence");
+
+        let sentence = context.sentence.clone();
+        for param in context.parameters.iter_mut() {
+            let (Some(value), Some(provenance)) = (&param.value, &mut param.provenance) else {
+                continue;
+            };
+            if !provenance.needs_grounding() {
+                continue;
+            }
+
+            match ground_value(&sentence, value, &self.config) {
+                Some(grounding) => {
+                    debug!(
+                        "Value of '{}' grounded ({:?})",
+                        param.name, grounding.method
+                    );
+                    provenance.verified = Some(true);
+                    if provenance.span.is_none() {
+                        provenance.span = grounding.span;
+                    }
+                }
+                None => {
+                    warn!(
+                        "Value of '{}' not found in the sentence: {}",
+                        param.name, value
+                    );
+                    match self.config.policy {
+                        GroundingPolicy::Mark => {
+                            provenance.verified = Some(false);
+                            provenance.confidence *= 0.5;
+                        }
+                        GroundingPolicy::Drop => {
+                            param.value = None;
+                            param.semantic_value = None;
+                            param.provenance = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        debug!("Grounding check completed");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "grounding_check"
+    }
+}
+
 // Step 3: Workflow Configuration (unchanged)
 const WORKFLOW_CONFIG: &str = r#"
 steps:
@@ -228,28 +607,156 @@ steps:
     timeout_secs: 10
   - name: json_generation
     enabled: true
+    model: sentence_to_json
     retry:
       max_attempts: 3
       delay_ms: 1000
     timeout_secs: 30
   - name: endpoint_matching
     enabled: true
+    model: find_endpoint
+    retry:
+      max_attempts: 2
+      delay_ms: 500
+    timeout_secs: 20
+  - name: field_matching
+    enabled: true
+    model: semantic_match
+    retry:
+      max_attempts: 2
+      delay_ms: 500
+    timeout_secs: 20
+  - name: grounding_check
+    enabled: true
+    grounding:
+      policy: mark
+"#;
+
+// Endpoint-first variant: the matched endpoint drives parameter extraction
+const ENDPOINT_FIRST_WORKFLOW_CONFIG: &str = r#"
+steps:
+  - name: configuration_loading
+    enabled: true
+    retry:
+      max_attempts: 3
+      delay_ms: 1000
+    timeout_secs: 10
+  - name: endpoint_matching
+    enabled: true
+    model: find_endpoint
     retry:
       max_attempts: 2
       delay_ms: 500
     timeout_secs: 20
+  - name: parameter_extraction
+    enabled: true
+    model: sentence_to_json
+    retry:
+      max_attempts: 3
+      delay_ms: 1000
+    timeout_secs: 30
+  - name: field_matching
+    enabled: true
+    model: semantic_match
+    retry:
+      max_attempts: 2
+      delay_ms: 500
+    timeout_secs: 20
+  - name: grounding_check
+    enabled: true
+    grounding:
+      policy: mark
+"#;
+
+// Tool-calling variant: one call selects the endpoint and fills in its parameters
+const TOOL_CALLING_WORKFLOW_CONFIG: &str = r#"
+steps:
+  - name: configuration_loading
+    enabled: true
+    retry:
+      max_attempts: 3
+      delay_ms: 1000
+    timeout_secs: 10
+  - name: tool_selection
+    enabled: true
+    model: find_endpoint
+    retry:
+      max_attempts: 2
+      delay_ms: 500
+    timeout_secs: 30
   - name: field_matching
     enabled: true
+    model: semantic_match
     retry:
       max_attempts: 2
       delay_ms: 500
     timeout_secs: 20
+  - name: grounding_check
+    enabled: true
+    grounding:
+      policy: mark
 "#;
 
+// Entry of the `models` section a step uses when its configuration names none;
+// `None` for steps that don't call a model
+fn default_model(step: &str) -> Option<&'static str> {
+    match step {
+        "json_generation" | "parameter_extraction" => Some("sentence_to_json"),
+        "endpoint_matching" | "tool_selection" => Some("find_endpoint"),
+        "field_matching" => Some("semantic_match"),
+        _ => None,
+    }
+}
+
+// Steps of the configured workflow mode, with the overrides of config.yaml applied
+fn configured_steps(
+    settings: &WorkflowSettings,
+) -> Result<Vec<StepConfig>, Box<dyn Error + Send + Sync>> {
+    let workflow_yaml = match settings.mode {
+        WorkflowMode::SentenceFirst => WORKFLOW_CONFIG,
+        WorkflowMode::EndpointFirst => ENDPOINT_FIRST_WORKFLOW_CONFIG,
+        WorkflowMode::ToolCalling => TOOL_CALLING_WORKFLOW_CONFIG,
+    };
+    let config: WorkflowConfig = serde_yaml::from_str(workflow_yaml)?;
+
+    Ok(config
+        .steps
+        .into_iter()
+        .map(|mut step_config| {
+            if let Some(overrides) = settings.steps.get(&step_config.name) {
+                step_config.apply_overrides(overrides);
+            }
+            step_config
+        })
+        .collect())
+}
+
+/// Routing of every model call the configured workflow can make, the
+/// stronger models of cascades included
+pub async fn configured_routings() -> Result<Vec<StepRouting>, Box<dyn Error + Send + Sync>> {
+    let settings = load_workflow_settings().await?;
+    let mut routings = Vec::new();
+
+    for step_config in configured_steps(&settings)? {
+        let Some(default_model) = default_model(&step_config.name) else {
+            continue;
+        };
+        if !step_config.enabled {
+            continue;
+        }
+        routings.push(step_config.routing(default_model));
+        if let Some(cascade) = step_config.cascade(default_model) {
+            routings.push(cascade.routing);
+        }
+    }
+
+    Ok(routings)
+}
+
 // Step 4: Updated analyze_sentence function with API URL parameter
 pub async fn analyze_sentence(
     sentence: &str,
-    provider: Arc<dyn ModelProvider>,
+    providers: Arc<ProviderRegistry>,
     api_url: Option<String>,
     email: &str,
 ) -> Result<AnalysisResult, Box<dyn Error + Send + Sync>> {
@@ -276,29 +783,55 @@ pub async fn analyze_sentence(
     }
 
     // Initialize workflow engine
-    let config: WorkflowConfig = serde_yaml::from_str(WORKFLOW_CONFIG)?;
+    let settings = load_workflow_settings().await?;
+    info!("Using {} workflow", settings.mode.as_str());
     let mut engine = WorkflowEngine::new();
+    let mut catalog_loading = None;
 
     // Register all steps
-    for step_config in config.steps {
+    for step_config in configured_steps(&settings)? {
+        let default_model = default_model(&step_config.name).unwrap_or_default();
         match step_config.name.as_str() {
             "configuration_loading" => {
+                let step = Arc::new(ConfigurationLoadingStep {
+                    api_url: api_url.clone(),
+                    email: email.to_string(),
+                    timeout: step_config.timeout_secs.map(Duration::from_secs),
+                });
+                catalog_loading = Some((step.clone(), step_config.retry.clone()));
+                engine.register_step(step_config, step);
+            }
+            "json_generation" => {
+                let routing = step_config.routing(default_model);
+                engine.register_step(step_config, Arc::new(JsonGenerationStep { routing }));
+            }
+            "endpoint_matching" => {
+                let routing = step_config.routing(default_model);
+                let cascade = step_config.cascade(default_model);
                 engine.register_step(
                     step_config,
-                    Arc::new(ConfigurationLoadingStep {
-                        api_url: api_url.clone(),
-                        email: email.to_string(),
-                    }),
+                    Arc::new(EndpointMatchingStep { routing, cascade }),
                 );
             }
-            "json_generation" => {
-                engine.register_step(step_config, Arc::new(JsonGenerationStep));
+            "parameter_extraction" => {
+                let routing = step_config.routing(default_model);
+                engine.register_step(step_config, Arc::new(ParameterExtractionStep { routing }));
             }
-            "endpoint_matching" => {
-                engine.register_step(step_config, Arc::new(EndpointMatchingStep));
+            "tool_selection" => {
+                let routing = step_config.routing(default_model);
+                engine.register_step(step_config, Arc::new(ToolSelectionStep { routing }));
             }
             "field_matching" => {
-                engine.register_step(step_config, Arc::new(FieldMatchingStep));
+                let routing = step_config.routing(default_model);
+                let cascade = step_config.cascade(default_model);
+                engine.register_step(
+                    step_config,
+                    Arc::new(FieldMatchingStep { routing, cascade }),
+                );
+            }
+            "grounding_check" => {
+                let config = step_config.grounding.clone().unwrap_or_default();
+                engine.register_step(step_config, Arc::new(GroundingCheckStep { config }));
             }
             _ => {
                 error!("Unknown step: {}", step_config.name);
@@ -307,10 +840,52 @@ pub async fn analyze_sentence(
         }
     }
 
-    // Execute workflow
-    let context = engine.execute(sentence.to_string(), provider).await?;
+    let prices = load_price_table().await?;
+    let cache_config = load_analysis_cache_config().await?;
+    if !cache_config.enabled {
+        let context = WorkflowContext::new(sentence.to_string(), providers);
+        return run_workflow(&engine, context, &prices).await;
+    }
+
+    // The catalog is part of the cache key, so it is loaded ahead of the
+    // workflow, under the retry and timeout of the configuration_loading step
+    let (step, retry) =
+        catalog_loading.ok_or("The configuration_loading step is not configured")?;
+    let catalog = step.fetch_catalog_with_retry(retry.as_ref()).await?;
+    let catalog_fingerprint = fingerprint(&[&serde_json::to_string(&catalog)?]);
+    let prompt_versions = PromptManager::new().await?.versions_fingerprint();
+    let key = AnalysisKey {
+        email,
+        catalog_fingerprint: &catalog_fingerprint,
+        workflow: settings.mode.as_str(),
+        prompt_versions: &prompt_versions,
+        sentence,
+    }
+    .fingerprint();
+
+    AnalysisCache::shared(cache_config)
+        .get_or_analyze(&key, || async {
+            let mut context = WorkflowContext::new(sentence.to_string(), providers);
+            context.endpoints_config = Some(catalog);
+            run_workflow(&engine, context, &prices).await
+        })
+        .await
+}
+
+async fn run_workflow(
+    engine: &WorkflowEngine,
+    context: WorkflowContext,
+    prices: &PriceTable,
+) -> Result<AnalysisResult, Box<dyn Error + Send + Sync>> {
+    let context = engine.execute(context).await?;
 
     // Convert workflow context to analysis result
+    let trace = context.trace.entries();
+    let usage = RunUsage::from_trace(&trace, prices);
+    info!(
+        "Analysis used {} input and {} output tokens (${:.6})",
+        usage.input_tokens, usage.output_tokens, usage.cost_usd
+    );
     Ok(AnalysisResult {
         json_output: context.json_output.ok_or("JSON output not available")?,
         endpoint_id: context.endpoint_id.ok_or("Endpoint ID not available")?,
@@ -318,5 +893,146 @@ pub async fn analyze_sentence(
             .endpoint_description
             .ok_or("Endpoint description not available")?,
         parameters: context.parameters,
+        escalations: trace
+            .iter()
+            .filter(|entry| matches!(entry.event, TraceEvent::Escalated { .. }))
+            .count() as u32,
+        trace,
+        cached: false,
+        usage,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::ModelProvider;
+    use crate::models::ModelsConfig;
+    use crate::workflow::trace::ExecutionTrace;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Answers every prompt with the same text, or fails when there is none
+    struct FixedProvider(Option<&'static str>, AtomicUsize);
+
+    #[async_trait]
+    impl ModelProvider for FixedProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0
+                .map(str::to_string)
+                .ok_or_else(|| "connection refused".into())
+        }
+    }
+
+    fn routing(provider: &str) -> StepRouting {
+        StepRouting {
+            model: "find_endpoint".to_string(),
+            provider: Some(provider.to_string()),
+            hedge: None,
+            truncation: Default::default(),
+        }
+    }
+
+    async fn match_endpoint(
+        cheap: Arc<FixedProvider>,
+        strong: Arc<FixedProvider>,
+    ) -> (
+        Result<WorkflowContext, Box<dyn Error + Send + Sync>>,
+        Vec<TraceEntry>,
+    ) {
+        let mut registry = ProviderRegistry::new("cheap");
+        registry.register("cheap", cheap);
+        registry.register("strong", strong);
+        let mut context = WorkflowContext::new("email alice".to_string(), Arc::new(registry));
+        context.models_config = Some(
+            serde_yaml::from_str::<ModelsConfig>(
+                "find_endpoint: {ollama: llama2, temperature: 0.1, max_tokens: 10}",
+            )
+            .unwrap(),
+        );
+        context.endpoints_config = Some(ConfigFile {
+            endpoints: vec![Endpoint {
+                id: "send_email".to_string(),
+                text: "send email".to_string(),
+                description: "Send an email".to_string(),
+                parameters: vec![],
+            }],
+        });
+
+        let step = EndpointMatchingStep {
+            routing: routing("cheap"),
+            cascade: Some(Cascade {
+                routing: routing("strong"),
+                config: serde_yaml::from_str("provider: strong").unwrap(),
+            }),
+        };
+        let trace = ExecutionTrace::default();
+        let result = trace
+            .scope(async {
+                step.execute(&mut context).await?;
+                Ok(context)
+            })
+            .await;
+        (result, trace.entries())
+    }
+
+    #[tokio::test]
+    async fn test_unusable_answers_escalate() {
+        let strong = Arc::new(FixedProvider(Some("send email"), AtomicUsize::new(0)));
+        let cheap = Arc::new(FixedProvider(Some("no idea"), AtomicUsize::new(0)));
+        let (context, trace) = match_endpoint(cheap, strong.clone()).await;
+        assert_eq!(context.unwrap().endpoint_id.as_deref(), Some("send_email"));
+        assert_eq!(strong.1.load(Ordering::SeqCst), 1);
+        assert!(trace.iter().any(|entry| matches!(
+            entry.event,
+            TraceEvent::Escalated {
+                reason: EscalationReason::SchemaValidation,
+                ..
+            }
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_provider_errors_do_not_escalate() {
+        let strong = Arc::new(FixedProvider(Some("send email"), AtomicUsize::new(0)));
+        let cheap = Arc::new(FixedProvider(None, AtomicUsize::new(0)));
+        let (context, trace) = match_endpoint(cheap, strong.clone()).await;
+        assert_eq!(context.unwrap_err().to_string(), "connection refused");
+        assert_eq!(strong.1.load(Ordering::SeqCst), 0);
+        assert!(!trace
+            .iter()
+            .any(|entry| matches!(entry.event, TraceEvent::Escalated { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_catalog_fetch_is_retried_within_the_step_timeout() {
+        // Accepts connections and never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let step = ConfigurationLoadingStep {
+            api_url: Some(format!("http://{}", listener.local_addr().unwrap())),
+            email: "user@example.com".to_string(),
+            timeout: Some(Duration::from_millis(100)),
+        };
+        let retry = RetryConfig {
+            max_attempts: 2,
+            delay_ms: 50,
+        };
+
+        let started = std::time::Instant::now();
+        let error = step
+            .fetch_catalog_with_retry(Some(&retry))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The endpoint service didn't answer within 100ms"
+        );
+        assert!(started.elapsed() >= Duration::from_millis(250));
+    }
+}
@@ -1,20 +1,12 @@
 // src/cli.rs - Updated to make email required
-use clap::{Parser, ValueEnum};
-use std::{error::Error, sync::Arc};
+use clap::Parser;
+use std::{error::Error, path::PathBuf, sync::Arc};
 use tracing::{error, info};
 
 use crate::endpoint_client::get_default_api_url;
 use crate::utils::email::validate_email;
-use crate::{analyze_sentence::analyze_sentence, models::providers::ModelProvider};
-
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
-#[clap(rename_all = "lowercase")]
-pub enum ProviderType {
-    /// Use Ollama (local models)
-    Ollama,
-    /// Use Claude API (requires API key in .env)
-    Claude,
-}
+use crate::workflow::trace::TraceEvent;
+use crate::{analyze_sentence::analyze_sentence, models::providers::ProviderRegistry};
 
 pub fn display_custom_help() {
     println!("
@@ -24,8 +16,9 @@ pub fn display_custom_help() {
 ╰───────────────────────────────────────────────╯
 
 ARGUMENTS:
-  --provider TYPE    Select which LLM provider to use (default: claude)
-                     Options: ollama, claude
+  --provider NAME    Provider instance to use (default: claude)
+                     Any name under `providers` in config.yaml,
+                     e.g. ollama, claude, openai
 
   --email ADDRESS    Your email address 
                      (REQUIRED ONLY when analyzing a sentence)
@@ -36,6 +29,10 @@ ARGUMENTS:
   --port PORT        Override gRPC server port
                      Default: From config.yaml
 
+  --record FILE      Record model responses to a cassette file
+  --replay FILE      Replay model responses from a cassette file
+  --strict           With --replay, fail on prompts not in the cassette
+
 USAGE EXAMPLES:
   1. Start gRPC server (no email required):
      semantic --provider ollama
@@ -46,6 +43,9 @@ USAGE EXAMPLES:
   3. Use remote endpoints:
      semantic --provider claude --api http://example.com:50053 --email user@example.com \"analyze this\"
 
+  4. Replay recorded responses offline:
+     semantic --provider ollama --replay cassette.json --strict --email user@example.com \"analyze this\"
+
 For more information, use the standard help:
   semantic --help
 ");
@@ -60,7 +60,7 @@ For more information, use the standard help:
 {about}
 
 [REQUIRED PARAMETERS]
---provider TYPE    : ollama or claude
+--provider NAME    : provider instance from config.yaml (ollama, claude, ...)
 --email ADDRESS    : valid email address
 
 {usage-heading} {usage}
@@ -71,9 +71,9 @@ pub struct Cli {
     /// The sentence to analyze (if not provided, starts gRPC server)
     pub prompt: Option<String>,
 
-    /// Select which LLM provider to use: 'ollama' or 'claude'
-    #[arg(long, value_enum, value_name = "TYPE", default_value = "claude")]
-    pub provider: ProviderType,
+    /// Name of the provider instance to use, a section of `providers` in config.yaml
+    #[arg(long, value_name = "NAME", default_value = "claude")]
+    pub provider: String,
 
     /// Remote API endpoint for fetching endpoint definitions (optional)
     #[arg(long, value_name = "URL")]
@@ -90,11 +90,23 @@ pub struct Cli {
     /// Override gRPC server port (default from config.yaml)
     #[arg(long, value_name = "PORT")]
     pub port: Option<u16>,
+
+    /// Record every model call and its response to a cassette file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Serve model responses from a cassette file recorded with --record
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// With --replay, fail on prompts missing from the cassette instead of calling the live model
+    #[arg(long, requires = "replay")]
+    pub strict: bool,
 }
 
 pub async fn handle_cli(
     mut cli: Cli,
-    provider: Arc<dyn ModelProvider>,
+    providers: Arc<ProviderRegistry>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     if let Some(prompt) = cli.prompt.clone() {
         // Email is required when analyzing a sentence
@@ -118,14 +130,7 @@ pub async fn handle_cli(
             }
         };
 
-        match cli.provider {
-            ProviderType::Claude => {
-                info!("Using Claude API for analysis");
-            }
-            ProviderType::Ollama => {
-                info!("Using self-hosted Ollama models for analysis");
-            }
-        };
+        info!("Using provider {} for analysis", cli.provider);
 
         // If API URL not provided in CLI, try to get default from config
         if cli.api.is_none() {
@@ -152,7 +157,7 @@ pub async fn handle_cli(
         info!("Analyzing prompt via CLI: {}", prompt);
 
         // Pass the API URL and email to analyze_sentence
-        let result = analyze_sentence(&prompt, provider, cli.api, &email).await?;
+        let result = analyze_sentence(&prompt, providers, cli.api, &email).await?;
 
         println!("\nAnalysis Results:");
         println!(
@@ -165,6 +170,52 @@ pub async fn handle_cli(
             if let Some(semantic) = param.semantic_value {
                 println!("  Semantic Match: {}", semantic);
             }
+            if let Some(value) = param.value.filter(|v| !v.is_string()) {
+                println!("  Typed Value: {}", value);
+            }
+            if let Some(provenance) = param.provenance {
+                println!(
+                    "  Source: {:?} (confidence {:.2})",
+                    provenance.source, provenance.confidence
+                );
+                if provenance.verified == Some(false) {
+                    println!("  Warning: value not found in the sentence");
+                }
+            }
+        }
+
+        // Only failover chains report which provider answered
+        for entry in &result.trace {
+            if let TraceEvent::ProviderAnswered { provider, model } = &entry.event {
+                println!(
+                    "\nAnswered by {} ({}) for {}",
+                    provider,
+                    model,
+                    entry.step.as_deref().unwrap_or("-")
+                );
+            }
+        }
+
+        if result.cached {
+            println!("\nServed from the analysis cache");
+        }
+
+        let usage = &result.usage;
+        if usage.input_tokens + usage.output_tokens > 0 {
+            println!(
+                "\nTokens: {} input, {} output{} - cost ${:.6}",
+                usage.input_tokens,
+                usage.output_tokens,
+                if usage.estimated { " (estimated)" } else { "" },
+                usage.cost_usd
+            );
+        }
+
+        if result.escalations > 0 {
+            println!(
+                "\nEscalated to a stronger model {} time(s)",
+                result.escalations
+            );
         }
 
         println!("\nRaw JSON Output:");
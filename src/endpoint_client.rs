@@ -36,6 +36,10 @@ pub fn convert_remote_endpoints(
                             required: Some(rp.required),
                             alternatives: Some(rp.alternatives),
                             semantic_value: None,
+                            param_type: Some(rp.r#type).filter(|t| !t.is_empty()),
+                            value: None,
+                            default: rp.default_value.map(prost_value_to_json),
+                            provenance: None,
                         })
                         .collect(),
                 })
@@ -43,6 +47,32 @@ pub fn convert_remote_endpoints(
         .collect()
 }
 
+// Converts a protobuf `google.protobuf.Value` into its JSON counterpart
+fn prost_value_to_json(value: prost_types::Value) -> serde_json::Value {
+    use prost_types::value::Kind;
+
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::BoolValue(b)) => serde_json::Value::Bool(b),
+        // Whole numbers stay integers, as they are in YAML catalogs
+        Some(Kind::NumberValue(n)) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            serde_json::Value::from(n as i64)
+        }
+        Some(Kind::NumberValue(n)) => serde_json::Value::from(n),
+        Some(Kind::StringValue(s)) => serde_json::Value::String(s),
+        Some(Kind::ListValue(list)) => {
+            serde_json::Value::Array(list.values.into_iter().map(prost_value_to_json).collect())
+        }
+        Some(Kind::StructValue(object)) => serde_json::Value::Object(
+            object
+                .fields
+                .into_iter()
+                .map(|(k, v)| (k, prost_value_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
 /// Check if the endpoint service is available
 pub async fn check_endpoint_service_health(
     addr: &str,
@@ -137,3 +167,39 @@ pub async fn get_default_endpoints(
 
     Ok(all_endpoints)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_parameter_types_and_defaults() {
+        let group = endpoint::ApiGroup {
+            endpoints: vec![Endpoint {
+                id: "create_invoice".to_string(),
+                parameters: vec![
+                    endpoint::Parameter {
+                        name: "amount".to_string(),
+                        r#type: "number".to_string(),
+                        ..Default::default()
+                    },
+                    endpoint::Parameter {
+                        name: "note".to_string(),
+                        default_value: Some(prost_types::Value {
+                            kind: Some(prost_types::value::Kind::StringValue("n/a".to_string())),
+                        }),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let endpoints = convert_remote_endpoints(vec![group]);
+        let parameters = &endpoints[0].parameters;
+        assert_eq!(parameters[0].param_type.as_deref(), Some("number"));
+        assert_eq!(parameters[1].param_type, None);
+        assert_eq!(parameters[0].default, None);
+        assert_eq!(parameters[1].default, Some(serde_json::json!("n/a")));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::endpoint_client::verify_endpoints_configuration;
 use crate::models::config::load_server_config;
-use crate::models::providers::ModelProvider;
+use crate::models::providers::ProviderRegistry;
 use crate::sentence_service::sentence::sentence_service_server::SentenceServiceServer;
 use crate::sentence_service::SentenceAnalyzeService;
 use std::sync::Arc;
@@ -12,7 +12,7 @@ use tracing::{error, info};
 
 // In src/grpc_server.rs
 pub async fn start_sentence_grpc_server(
-    provider: Arc<dyn ModelProvider>,
+    providers: Arc<ProviderRegistry>,
     api_url: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Load server configuration
@@ -64,8 +64,8 @@ pub async fn start_sentence_grpc_server(
 
     tracing::info!("Starting semantic gRPC server on {}", addr);
 
-    // Use the provider that was passed in from main.rs
-    let sentence_service = SentenceAnalyzeService::new(provider, api_url);
+    // Use the providers that were passed in from main.rs
+    let sentence_service = SentenceAnalyzeService::new(providers, api_url);
     let service = SentenceServiceServer::new(sentence_service);
 
     match Server::builder()
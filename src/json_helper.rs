@@ -1,58 +1,56 @@
-use regex::Regex;
+use crate::json_repair::recover_json;
+use crate::metrics;
+use crate::reasoning;
+use crate::workflow::trace::{self, TraceEvent};
 use serde_json::Value;
 use std::error::Error;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 pub fn sanitize_json(raw_text: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
     //debug!("Sanitizing JSON from raw text:\n{}", raw_text);
 
-    // Extract JSON using regex
-    let re = Regex::new(r"\{[\s\S]*\}")?;
-    let json_str = re
-        .find(raw_text)
-        .ok_or_else(|| {
-            error!("No JSON found in response: {}", raw_text);
-            "No JSON structure found in response"
-        })?
-        .as_str();
+    // Drop reasoning, its braces would be taken for the JSON
+    let answer = reasoning::without_reasoning(raw_text);
 
-    // Remove trailing commas before parsing
-    let cleaned_json = remove_trailing_commas(json_str);
-
-    debug!("Cleaned JSON string:\n{}", cleaned_json);
-
-    // Parse the JSON
-    let parsed_json: Value = serde_json::from_str(json_str).map_err(|e| {
-        error!("Failed to parse JSON: {}\nRaw JSON string: {}", e, json_str);
-        format!("Failed to parse JSON: {}. Raw JSON: {}", e, json_str)
+    let recovered = recover_json(&answer).map_err(|e| {
+        error!("{}\nRaw response: {}", e, raw_text);
+        e
     })?;
 
-    debug!("Successfully parsed JSON");
-    Ok(parsed_json)
-}
-
-fn remove_trailing_commas(json_str: &str) -> String {
-    // First, handle single-line trailing commas
-    let single_line_fixed = json_str.replace(", }", "}").replace(",}", "}");
+    if !recovered.repairs.is_empty() {
+        let repairs: Vec<&str> = recovered.repairs.iter().map(|r| r.as_str()).collect();
+        warn!("Repaired JSON from the model: {}", repairs.join(", "));
+        for repair in &repairs {
+            metrics::increment(&metrics::labeled("json_repairs", &[("repair", repair)]), 1);
+        }
+        trace::record(TraceEvent::JsonRepaired {
+            repairs: recovered.repairs,
+        });
+    }
 
-    // Then handle multi-line trailing commas with regex
-    let re = Regex::new(r",(\s*[\}\]])").expect("Invalid regex pattern");
-    re.replace_all(&single_line_fixed, "$1").to_string()
+    debug!("Successfully parsed JSON");
+    Ok(recovered.value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::json_repair::Repair;
 
-    #[test]
-    fn test_remove_trailing_commas() {
+    #[tokio::test]
+    async fn test_repairs_are_traced() {
         let input = r#"{
             "customer_id": "Josiane",
         }"#;
-        let expected = r#"{
-            "customer_id": "Josiane"
-        }"#;
-        assert_eq!(remove_trailing_commas(input), expected);
+        let trace = trace::ExecutionTrace::default();
+        let json = trace.scope(async { sanitize_json(input) }).await.unwrap();
+        assert_eq!(json["customer_id"], "Josiane");
+        assert_eq!(
+            trace.entries()[0].event,
+            TraceEvent::JsonRepaired {
+                repairs: vec![Repair::TrailingCommas]
+            }
+        );
     }
 
     #[test]
@@ -93,4 +91,11 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap()["nested"]["array"][0], 1);
     }
+
+    #[test]
+    fn test_sanitize_json_after_reasoning() {
+        let input = "<think>\nThe sentence names {customer} and {amount}, so\n{\"draft\": 1}\n</think>\n```json\n{\"customer_id\": \"Josiane\"}\n```";
+        let result = sanitize_json(input).unwrap();
+        assert_eq!(result, serde_json::json!({"customer_id": "Josiane"}));
+    }
 }
@@ -0,0 +1,477 @@
+// src/json_repair.rs - Tolerant recovery of the JSON object in a model response
+
+use crate::reasoning;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+/// Fix applied to a response to get a JSON object out of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Repair {
+    /// The object was wrapped in a markdown code fence
+    CodeFence,
+    /// More objects followed the one that was used
+    MultipleObjects,
+    SingleQuotes,
+    /// Typographic quotes (“ ” ‘ ’) around keys or strings
+    SmartQuotes,
+    UnquotedKeys,
+    /// `//` and `/* */` comments
+    Comments,
+    TrailingCommas,
+    /// The response ended before the closing quotes and brackets
+    Truncated,
+}
+
+impl Repair {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Repair::CodeFence => "code_fence",
+            Repair::MultipleObjects => "multiple_objects",
+            Repair::SingleQuotes => "single_quotes",
+            Repair::SmartQuotes => "smart_quotes",
+            Repair::UnquotedKeys => "unquoted_keys",
+            Repair::Comments => "comments",
+            Repair::TrailingCommas => "trailing_commas",
+            Repair::Truncated => "truncated",
+        }
+    }
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered {
+    pub value: Value,
+    /// Repairs that were needed, empty when the object was valid JSON
+    pub repairs: Vec<Repair>,
+}
+
+/// Finds the first JSON object of `text` (the content of a code fence when
+/// there is one) and parses it, tolerating the usual mistakes of models
+pub fn recover_json(text: &str) -> Result<Recovered, Box<dyn Error + Send + Sync>> {
+    let unfenced = reasoning::strip_code_fences(text);
+    if unfenced != text.trim() {
+        if let Ok(mut recovered) = first_object(&unfenced) {
+            recovered.repairs.insert(0, Repair::CodeFence);
+            return Ok(recovered);
+        }
+    }
+    Ok(first_object(text)?)
+}
+
+fn first_object(text: &str) -> Result<Recovered, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut first_error = None;
+
+    if let Some((value, parser)) = next_object(&chars, 0, &mut first_error) {
+        let mut repairs = parser.repairs;
+        if next_object(&chars, parser.pos, &mut None).is_some() {
+            repairs.insert(Repair::MultipleObjects);
+        }
+        return Ok(Recovered {
+            value,
+            repairs: repairs.into_iter().collect(),
+        });
+    }
+
+    Err(match first_error {
+        Some(e) => format!("Failed to parse JSON: {}", e),
+        None => "No JSON structure found in response".to_string(),
+    })
+}
+
+// The first object from `from` on, with the parser left after it. A candidate
+// that fails resumes the scan where it stopped, so the text is read once.
+fn next_object<'a>(
+    chars: &'a [char],
+    from: usize,
+    first_error: &mut Option<String>,
+) -> Option<(Value, Parser<'a>)> {
+    let mut pos = from;
+    while let Some(start) = (pos..chars.len()).find(|&i| chars[i] == '{') {
+        // Prose ending in a brace has no object to recover
+        if chars[start + 1..].iter().all(|c| c.is_whitespace()) {
+            first_error.get_or_insert_with(|| "the response ends with a lone '{'".to_string());
+            return None;
+        }
+
+        let mut parser = Parser::new(chars, start);
+        match parser.object() {
+            Ok(value) => return Some((value, parser)),
+            Err(Stop::Invalid(e)) => {
+                first_error.get_or_insert(e);
+                pos = parser.pos.max(start + 1);
+            }
+            Err(Stop::End) => unreachable!("objects recover from a cut"),
+        }
+    }
+    None
+}
+
+enum Stop {
+    /// The text ended before a value could be read
+    End,
+    Invalid(String),
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+    repairs: BTreeSet<Repair>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(chars: &'a [char], pos: usize) -> Self {
+        Self {
+            chars,
+            pos,
+            repairs: BTreeSet::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn invalid<T>(&self, expected: &str) -> Result<T, Stop> {
+        Err(Stop::Invalid(match self.peek() {
+            Some(c) => format!(
+                "expected {} at character {}, found '{}'",
+                expected, self.pos, c
+            ),
+            None => format!("expected {} at the end", expected),
+        }))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            let next = self.chars.get(self.pos + 1).copied();
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '/' && next == Some('/') {
+                self.repairs.insert(Repair::Comments);
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c == '/' && next == Some('*') {
+                self.repairs.insert(Repair::Comments);
+                self.pos += 2;
+                while self.pos < self.chars.len()
+                    && !(self.chars[self.pos] == '*' && self.chars.get(self.pos + 1) == Some(&'/'))
+                {
+                    self.pos += 1;
+                }
+                self.pos = (self.pos + 2).min(self.chars.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// What was read so far, when the text ends inside a container
+    fn cut(&mut self, value: Value) -> Result<Value, Stop> {
+        self.repairs.insert(Repair::Truncated);
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, Stop> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(Stop::End),
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some(c) if is_quote(c) => {
+                let (text, closed) = self.string();
+                if !closed {
+                    self.repairs.insert(Repair::Truncated);
+                }
+                Ok(Value::String(text))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.literal(),
+            Some(_) => self.invalid("a value"),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, Stop> {
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.cut(Value::Object(map)),
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(map));
+                }
+                _ => {}
+            }
+
+            // A member cut before its value is complete is dropped
+            let key = match self.key() {
+                Err(Stop::End) => return self.cut(Value::Object(map)),
+                key => key?,
+            };
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.cut(Value::Object(map)),
+                Some(':') => self.pos += 1,
+                Some(_) => return self.invalid("':'"),
+            }
+            let value = match self.value() {
+                Err(Stop::End) => return self.cut(Value::Object(map)),
+                value => value?,
+            };
+            map.insert(key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.cut(Value::Object(map)),
+                Some(',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some('}') {
+                        self.repairs.insert(Repair::TrailingCommas);
+                    }
+                }
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(map));
+                }
+                Some(_) => return self.invalid("',' or '}'"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Stop> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.cut(Value::Array(items)),
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => {}
+            }
+
+            match self.value() {
+                Err(Stop::End) => return self.cut(Value::Array(items)),
+                value => items.push(value?),
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.cut(Value::Array(items)),
+                Some(',') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(']') {
+                        self.repairs.insert(Repair::TrailingCommas);
+                    }
+                }
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                Some(_) => return self.invalid("',' or ']'"),
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String, Stop> {
+        match self.peek() {
+            Some(c) if is_quote(c) => match self.string() {
+                (key, true) => Ok(key),
+                (_, false) => Err(Stop::End),
+            },
+            Some(c) if is_identifier(c) => {
+                self.repairs.insert(Repair::UnquotedKeys);
+                let start = self.pos;
+                while self.peek().is_some_and(is_identifier) {
+                    self.pos += 1;
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+            _ => self.invalid("a key"),
+        }
+    }
+
+    /// Reads a string and whether it was closed before the end of the text
+    fn string(&mut self) -> (String, bool) {
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+        let closing: &[char] = match quote {
+            '"' => &['"'],
+            '\'' => {
+                self.repairs.insert(Repair::SingleQuotes);
+                &['\'']
+            }
+            '“' | '”' => {
+                self.repairs.insert(Repair::SmartQuotes);
+                &['”', '“']
+            }
+            _ => {
+                self.repairs.insert(Repair::SmartQuotes);
+                &['’', '‘']
+            }
+        };
+
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if closing.contains(&c) {
+                return (text, true);
+            }
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+            let Some(escaped) = self.peek() else {
+                break;
+            };
+            self.pos += 1;
+            match escaped {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                'r' => text.push('\r'),
+                'b' => text.push('\u{8}'),
+                'f' => text.push('\u{c}'),
+                'u' => match self.unicode_escape() {
+                    Some(c) => text.push(c),
+                    None => break,
+                },
+                // \" \\ \/ and the escapes JSON doesn't have, like \'
+                other => text.push(other),
+            }
+        }
+        (text, false)
+    }
+
+    // The code point of `\uXXXX`, or of a `\uXXXX\uXXXX` surrogate pair
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return Some(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        if self.chars.get(self.pos..self.pos + 2) == Some(&['\\', 'u']) {
+            self.pos += 2;
+            let low = self.hex4()?;
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+            return Some(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        Some(char::REPLACEMENT_CHARACTER)
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits: String = self.chars.get(self.pos..self.pos + 4)?.iter().collect();
+        self.pos += 4;
+        Some(u32::from_str_radix(&digits, 16).unwrap_or(0xFFFD))
+    }
+
+    fn number(&mut self) -> Result<Value, Stop> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse::<Number>() {
+            Ok(number) => Ok(Value::Number(number)),
+            Err(_) if self.peek().is_none() => Err(Stop::End),
+            Err(_) => {
+                self.pos = start;
+                self.invalid("a number")
+            }
+        }
+    }
+
+    fn literal(&mut self) -> Result<Value, Stop> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        match word.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "null" => Ok(Value::Null),
+            _ if self.peek().is_none()
+                && ["true", "false", "null"]
+                    .iter()
+                    .any(|literal| literal.starts_with(&word)) =>
+            {
+                Err(Stop::End)
+            }
+            _ => {
+                self.pos = start;
+                self.invalid("a value")
+            }
+        }
+    }
+}
+
+fn is_quote(c: char) -> bool {
+    matches!(c, '"' | '\'' | '“' | '”' | '‘' | '’')
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Model outputs that broke parsing, with the object they should give
+    #[derive(Deserialize)]
+    struct Case {
+        name: String,
+        response: String,
+        expected: Value,
+        #[serde(default)]
+        repairs: Vec<Repair>,
+    }
+
+    #[test]
+    fn test_corpus() {
+        let cases: Vec<Case> =
+            serde_yaml::from_str(include_str!("../test/json_corpus.yaml")).unwrap();
+        assert!(!cases.is_empty());
+        for case in cases {
+            let recovered =
+                recover_json(&case.response).unwrap_or_else(|e| panic!("{}: {}", case.name, e));
+            assert_eq!(recovered.value, case.expected, "{}", case.name);
+            assert_eq!(recovered.repairs, case.repairs, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn test_unrecoverable_responses() {
+        let error = recover_json("I could not find any parameters.").unwrap_err();
+        assert_eq!(error.to_string(), "No JSON structure found in response");
+
+        let error = recover_json(r#"{"customer_id" "Josiane"}"#).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Failed to parse JSON: expected ':'"));
+
+        let error = recover_json("Here are the parameters: {").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to parse JSON: the response ends with a lone '{'"
+        );
+    }
+}
@@ -1,20 +1,33 @@
 // src/main.rs
+mod analysis_cache;
 mod analyze_sentence;
 mod call_ollama;
 mod cli;
 mod endpoint_client;
 mod grpc_server;
 mod json_helper;
+mod json_repair;
+mod metrics;
 mod models;
 mod prompts;
+mod readiness;
+mod reasoning;
 mod sentence_service;
+mod usage;
 mod utils;
 
 use std::sync::Arc;
 mod workflow;
-use crate::models::config::load_models_config;
-use crate::models::providers::{create_provider, ModelProvider, ProviderConfig};
-use cli::ProviderType;
+use crate::analyze_sentence::configured_routings;
+use crate::models::config::{
+    load_cache_config, load_models_config, load_provider_configs, load_readiness_config,
+};
+use crate::models::providers::cache::{CachingProvider, ResponseCache};
+use crate::models::providers::cassette::{Cassette, RecordingProvider, ReplayProvider};
+use crate::models::providers::rate_limit::RateLimitedProvider;
+use crate::models::providers::{
+    create_provider, ModelProvider, ProviderConfig, ProviderKind, ProviderRegistry,
+};
 
 use clap::Parser;
 use cli::{display_custom_help, handle_cli, Cli};
@@ -22,10 +35,9 @@ use dotenv::dotenv;
 use endpoint_client::get_default_api_url;
 use grpc_logger::load_config;
 use grpc_server::start_sentence_grpc_server;
-use std::env;
 use std::error::Error;
 use tokio::signal;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{EnvFilter, Registry};
@@ -82,43 +94,161 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     };
 
     // Load model configuration
-    let _models_config = load_models_config().await?;
-
-    // Initialize provider based on CLI provider type
-    let use_claude = matches!(cli.provider, ProviderType::Claude);
-
-    // Create the provider
-    let provider: Box<dyn ModelProvider> = if use_claude {
-        // Load .env file and check for Claude API key
-        dotenv().ok();
-        match env::var("CLAUDE_API_KEY") {
-            Ok(api_key) => {
-                info!("Using Claude API");
-                let config = ProviderConfig {
-                    enabled: true,
-                    api_key: Some(api_key),
-                    host: None,
-                };
-                create_provider(&config).expect("Failed to create Claude provider")
+    let models_config = load_models_config().await?;
+
+    // Load .env file so the Claude API key is available
+    dotenv().ok();
+
+    let mut provider_configs = load_provider_configs().await?;
+    // Self-hosted Ollama is registered even without a section so workflow
+    // steps can route to it
+    provider_configs
+        .entry("ollama".to_string())
+        .or_insert_with(|| ProviderConfig {
+            enabled: true,
+            ..Default::default()
+        });
+
+    let replay_cassette = match &cli.replay {
+        Some(path) => Some(Cassette::open(path).await?),
+        None => None,
+    };
+
+    // Every section of `providers` is a named instance; enabled ones are
+    // registered, and the one selected with --provider is the default
+    let mut registry = ProviderRegistry::new(&cli.provider);
+    for (name, config) in &provider_configs {
+        let selected = *name == cli.provider;
+        if config.kind == Some(ProviderKind::Failover) || !(config.enabled || selected) {
+            continue;
+        }
+        let provider = config.resolve(name).and_then(|config| {
+            create_provider(&ProviderConfig {
+                enabled: true,
+                ..config
+            })?
+            .ok_or_else(|| format!("Cannot tell the type of provider '{}'", name).into())
+        });
+        match provider {
+            Ok(provider) => {
+                info!("Registered provider {}", name);
+                registry.register(name, Arc::from(provider));
             }
-            Err(_) => {
-                error!("Claude API key not found in .env file. Please add CLAUDE_API_KEY to .env");
+            Err(e) => match &replay_cassette {
+                // Recorded runs can be reproduced without the provider's credentials
+                Some(cassette) => {
+                    info!("Provider {} is replay-only: {}", name, e);
+                    let replay = ReplayProvider::new(name, None, cassette.clone(), true);
+                    registry.register(name, Arc::new(replay));
+                }
+                None if selected => {
+                    error!("Provider {} is not available: {}", name, e);
+                    std::process::exit(1);
+                }
+                None => warn!("Provider {} is not available: {}", name, e),
+            },
+        }
+    }
+
+    // The server finds out about missing models and bad keys now rather than
+    // on the first request. Replayed runs don't call the providers.
+    let readiness_config = load_readiness_config().await?;
+    if readiness_config.enabled && cli.prompt.is_none() && cli.replay.is_none() {
+        // Only the models the workflow steps route to; other providers may
+        // be registered without being used
+        let routings = configured_routings().await?;
+        let targets = readiness::targets(&routings, &cli.provider, &provider_configs);
+        let report =
+            readiness::check_models(&registry, &models_config, &targets, &readiness_config).await;
+        let failures = report.failures();
+        if !failures.is_empty() {
+            if readiness_config.fail_fast {
+                error!("Models not ready: {}", failures.join("; "));
                 std::process::exit(1);
             }
+            warn!(
+                "Serving degraded, models not ready: {}",
+                failures.join("; ")
+            );
         }
+        readiness::publish(report);
+    }
+
+    // Limits apply to each provider across all workflows, failover chains
+    // included, so they wrap the providers before the chains are built.
+    // Chain members pass their 429s to the chain instead of retrying.
+    let mut registry = registry.wrap(|name, provider| {
+        match provider_configs
+            .get(name)
+            .and_then(|config| config.rate_limit.clone())
+        {
+            Some(limits) => {
+                info!("Rate limiting {}: {:?}", name, limits);
+                Arc::new(RateLimitedProvider::new(name, provider, limits))
+            }
+            None => provider,
+        }
+    });
+
+    // Failover chains are built from the providers registered above
+    for (name, config) in &provider_configs {
+        if (config.enabled || *name == cli.provider) && config.kind == Some(ProviderKind::Failover)
+        {
+            registry.register_failover(name, &config.chain)?;
+            info!("Failover provider {}: {}", name, config.chain.join(" -> "));
+        }
+    }
+    if let Err(e) = registry.resolve(None) {
+        error!("{}. Configure it under `providers` in config.yaml", e);
+        std::process::exit(1);
+    }
+
+    // Record or replay model calls so runs can be reproduced offline
+    let registry = if let Some(path) = &cli.record {
+        let cassette = Cassette::open(path).await?;
+        info!("Recording model responses to {}", path.display());
+        registry.wrap(|name, provider| {
+            Arc::new(RecordingProvider::new(name, provider, cassette.clone()))
+        })
+    } else if let (Some(path), Some(cassette)) = (&cli.replay, replay_cassette) {
+        info!(
+            "Replaying model responses from {}{}",
+            path.display(),
+            if cli.strict { " (strict)" } else { "" }
+        );
+        registry.wrap(|name, provider| {
+            Arc::new(ReplayProvider::new(
+                name,
+                Some(provider),
+                cassette.clone(),
+                cli.strict,
+            ))
+        })
     } else {
-        // Use self-hosted Ollama
-        info!("Using self-hosted Ollama");
-        let config = ProviderConfig {
-            enabled: true,
-            host: Some("http://localhost:11434".to_string()),
-            api_key: None,
-        };
-        create_provider(&config).expect("Failed to create Ollama provider")
+        registry
     };
 
-    // Wrap the provider in an Arc so we can clone it
-    let provider_arc: Arc<dyn ModelProvider> = Arc::from(provider);
+    // Repeated prompts are answered from the response cache
+    let cache_config = load_cache_config().await?;
+    let registry = if cache_config.enabled {
+        info!(
+            "Caching model responses (capacity {}, ttl {}s{})",
+            cache_config.capacity,
+            cache_config.ttl_secs,
+            cache_config
+                .disk_path
+                .as_ref()
+                .map(|path| format!(", stored in {}", path.display()))
+                .unwrap_or_default()
+        );
+        let cache = Arc::new(ResponseCache::new(cache_config));
+        registry
+            .wrap(|name, provider| Arc::new(CachingProvider::new(name, provider, cache.clone())))
+    } else {
+        registry
+    };
+    info!("Available providers: {}", registry.names().join(", "));
+    let providers = Arc::new(registry);
 
     // Get API URL from CLI or config
     let api_url = if let Some(url) = cli.api.clone() {
@@ -139,7 +269,7 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     };
 
     //let app_state = AppState {
-    //    provider: provider_arc.clone(),
+    //    provider: providers.clone(),
     //    log_config: Arc::new(log_config),
     //    api_url: api_url.clone(),
     //};
@@ -148,23 +278,19 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     match cli.prompt {
         Some(_) => {
             // CLI mode with a prompt - email is required and validated in handle_cli
-            handle_cli(cli, provider_arc).await?;
+            handle_cli(cli, providers).await?;
         }
         None => {
             // Server mode - email is not needed
-            let provider_name = match cli.provider {
-                ProviderType::Claude => "Claude API",
-                ProviderType::Ollama => "Ollama self-hosted models",
-            };
             info!(
-                "No prompt provided, starting gRPC server with {}...",
-                provider_name
+                "No prompt provided, starting gRPC server with provider {}...",
+                cli.provider
             );
 
             // Start the gRPC server with our API URL if provided
             // Email is NOT needed for starting the server
             let grpc_server = tokio::spawn(async move {
-                if let Err(e) = start_sentence_grpc_server(provider_arc.clone(), api_url).await {
+                if let Err(e) = start_sentence_grpc_server(providers, api_url).await {
                     error!("gRPC server error: {:?}", e);
                 }
             });
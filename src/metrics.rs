@@ -0,0 +1,65 @@
+// src/metrics.rs - Process-wide counters, served by the GetMetrics RPC
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+fn counters() -> &'static Mutex<BTreeMap<String, u64>> {
+    static COUNTERS: OnceLock<Mutex<BTreeMap<String, u64>>> = OnceLock::new();
+    COUNTERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Adds `by` to a counter. Labels are part of the name, Prometheus style:
+/// `llm_cache_hits{provider="ollama"}`
+pub fn increment(name: &str, by: u64) {
+    *counters()
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_default() += by;
+}
+
+// Label values can come from requests (client ids), escaped as Prometheus does
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Counter name with labels, e.g. `labeled("llm_cache_hits", &[("provider", "ollama")])`
+pub fn labeled(name: &str, labels: &[(&str, &str)]) -> String {
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{}{{{}}}", name, labels)
+}
+
+pub fn snapshot() -> BTreeMap<String, u64> {
+    counters().lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_increment_labeled_counter() {
+        let name = labeled(
+            "test_counter",
+            &[("provider", "ollama"), ("model", "llama2")],
+        );
+        assert_eq!(name, r#"test_counter{provider="ollama",model="llama2"}"#);
+
+        increment(&name, 1);
+        increment(&name, 2);
+        assert_eq!(snapshot().get(&name), Some(&3));
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        let name = labeled("test_requests", &[("client_id", "a\"b\\c\nd")]);
+        assert_eq!(name, r#"test_requests{client_id="a\"b\\c\nd"}"#);
+    }
+}
@@ -1,17 +1,18 @@
 // src/models/config.rs
+use crate::analysis_cache::AnalysisCacheConfig;
+use crate::models::providers::cache::CacheConfig;
+use crate::models::providers::ProviderConfig;
 use crate::models::ModelsConfig;
+use crate::readiness::ReadinessConfig;
+use crate::usage::PriceTable;
+use crate::workflow::{StepOverrides, WorkflowMode};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::error::Error;
 use tracing::debug;
 
 // use super::ConfigFile;
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct Providers {}
-
-#[derive(Debug, Deserialize, Clone)]
-pub struct GrpcConfig {}
-
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub address: String,
@@ -23,20 +24,37 @@ pub struct EndpointClientConfig {
     pub default_address: String,
 }
 
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct WorkflowSettings {
+    #[serde(default)]
+    pub mode: WorkflowMode,
+    /// Per-step overrides keyed by step name
+    #[serde(default)]
+    pub steps: HashMap<String, StepOverrides>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub models: ModelsConfig,
     pub server: ServerConfig,
     pub endpoint_client: EndpointClientConfig,
+    #[serde(default)]
+    pub workflow: WorkflowSettings,
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderConfig>,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub analysis_cache: AnalysisCacheConfig,
+    /// Model prices used to compute the cost of an analysis
+    #[serde(default)]
+    pub pricing: PriceTable,
+    /// Startup checks of the configured models
+    #[serde(default)]
+    pub readiness: ReadinessConfig,
     // pub debug_mode: Option<DebugConfig>, // Optional to maintain backward compatibility
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct DebugConfig {
-    // pub enabled: bool,
-    // pub use_local_endpoints: bool,
-}
-
 pub async fn load_models_config() -> Result<ModelsConfig, Box<dyn Error + Send + Sync>> {
     let config_str = tokio::fs::read_to_string("config.yaml").await?;
     let config: Config = serde_yaml::from_str(&config_str)?;
@@ -66,6 +84,71 @@ pub async fn load_server_config() -> Result<ServerConfig, Box<dyn Error + Send +
     Ok(config.server)
 }
 
+// Load workflow settings from config file, defaulting to the sentence-first mode
+pub async fn load_workflow_settings() -> Result<WorkflowSettings, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!("Loaded workflow settings: {:#?}", config.workflow);
+
+    Ok(config.workflow)
+}
+
+// Load provider sections keyed by provider name
+pub async fn load_provider_configs(
+) -> Result<HashMap<String, ProviderConfig>, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!("Loaded provider configurations: {:#?}", config.providers);
+
+    Ok(config.providers)
+}
+
+// Load the model response cache settings, disabled when the section is missing
+pub async fn load_cache_config() -> Result<CacheConfig, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!("Loaded cache configuration: {:#?}", config.cache);
+
+    Ok(config.cache)
+}
+
+// Load the full-analysis cache settings, disabled when the section is missing
+pub async fn load_analysis_cache_config(
+) -> Result<AnalysisCacheConfig, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!(
+        "Loaded analysis cache configuration: {:#?}",
+        config.analysis_cache
+    );
+
+    Ok(config.analysis_cache)
+}
+
+// Load the model price table, empty (no cost computed) when the section is missing
+pub async fn load_price_table() -> Result<PriceTable, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!("Loaded price table: {:#?}", config.pricing);
+
+    Ok(config.pricing)
+}
+
+// Load the startup readiness settings, checks enabled when the section is missing
+pub async fn load_readiness_config() -> Result<ReadinessConfig, Box<dyn Error + Send + Sync>> {
+    let config_str = tokio::fs::read_to_string("config.yaml").await?;
+    let config: Config = serde_yaml::from_str(&config_str)?;
+
+    debug!("Loaded readiness configuration: {:#?}", config.readiness);
+
+    Ok(config.readiness)
+}
+
 // Load endpoint client configuration from config file
 pub async fn load_endpoint_client_config(
 ) -> Result<EndpointClientConfig, Box<dyn Error + Send + Sync>> {
@@ -1,23 +1,11 @@
 pub mod config;
+pub mod provenance;
 pub mod providers;
 
 pub use providers::ModelsConfig;
 use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Debug)]
-pub struct GenerateRequest {
-    pub model: String,
-    pub prompt: String,
-    pub stream: bool,
-    pub format: Option<String>,
-    pub temperature: f32,
-    pub max_tokens: u32,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct OllamaResponse {
-    //pub response: String,
-}
+use provenance::Provenance;
+use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Endpoint {
@@ -34,6 +22,17 @@ pub struct EndpointParameter {
     pub required: Option<bool>,
     pub alternatives: Option<Vec<String>>,
     pub semantic_value: Option<String>,
+    #[serde(default, rename = "type")]
+    pub param_type: Option<String>,
+    /// Typed value resolved for the parameter (arrays, numbers, objects...)
+    #[serde(default)]
+    pub value: Option<Value>,
+    /// Catalog value used when the sentence doesn't provide one
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// Where the resolved value came from
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -0,0 +1,122 @@
+use crate::utils::text_span::{locate_span, TextSpan};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How a parameter value was obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSource {
+    /// A generated field had exactly the parameter name
+    Exact,
+    /// A generated field matched one of the parameter alternatives or a nested path
+    Alias,
+    /// The model mapped a generated field onto the parameter
+    Semantic,
+    /// The schema-guided extractor filled the parameter slot directly
+    Extractor,
+    /// The catalog default of the parameter
+    Default,
+    /// Carried over from an earlier request of the same session
+    #[allow(dead_code)]
+    Session,
+    /// Supplied explicitly by the user
+    #[allow(dead_code)]
+    UserProvided,
+}
+
+impl ValueSource {
+    // Confidence before checking the value against the sentence
+    fn base_confidence(&self) -> f32 {
+        match self {
+            ValueSource::UserProvided => 1.0,
+            ValueSource::Exact => 0.95,
+            ValueSource::Extractor => 0.9,
+            ValueSource::Session => 0.85,
+            ValueSource::Alias => 0.85,
+            ValueSource::Semantic => 0.7,
+            ValueSource::Default => 0.5,
+        }
+    }
+}
+
+/// Where a parameter value came from and how much it can be trusted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: ValueSource,
+    pub confidence: f32,
+    /// Span of the value in the original sentence, when it appears verbatim
+    pub span: Option<TextSpan>,
+    /// Generated field the value was read from, when different from the parameter name
+    pub field: Option<String>,
+    /// Outcome of the grounding check, `None` when it didn't run
+    #[serde(default)]
+    pub verified: Option<bool>,
+}
+
+impl Provenance {
+    pub fn new(source: ValueSource, field: Option<String>, value: &Value, sentence: &str) -> Self {
+        let span = match value {
+            Value::String(s) => locate_span(sentence, s),
+            Value::Number(n) => locate_span(sentence, &n.to_string()),
+            _ => None,
+        };
+
+        // Values the model produced that can't be found in the sentence are less certain
+        let mut confidence = source.base_confidence();
+        if span.is_none() && !matches!(source, ValueSource::Default | ValueSource::UserProvided) {
+            confidence *= 0.75;
+        }
+
+        Self {
+            source,
+            confidence,
+            span,
+            field,
+            verified: None,
+        }
+    }
+
+    /// Whether the value is expected to appear in the sentence
+    pub fn needs_grounding(&self) -> bool {
+        !matches!(
+            self.source,
+            ValueSource::Default | ValueSource::Session | ValueSource::UserProvided
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_provenance_with_span() {
+        let sentence = "Send the report to alice@example.com";
+        let provenance = Provenance::new(
+            ValueSource::Exact,
+            None,
+            &json!("alice@example.com"),
+            sentence,
+        );
+        assert_eq!(provenance.span, Some(TextSpan { start: 19, end: 36 }));
+        assert_eq!(provenance.confidence, 0.95);
+    }
+
+    #[test]
+    fn test_provenance_without_span_lowers_confidence() {
+        let sentence = "Send the report to Alice";
+        let semantic = Provenance::new(
+            ValueSource::Semantic,
+            Some("recipient".to_string()),
+            &json!("alice@example.com"),
+            sentence,
+        );
+        assert_eq!(semantic.span, None);
+        assert!(semantic.confidence < ValueSource::Semantic.base_confidence());
+
+        // Defaults never appear in the sentence and are not penalized for it
+        let default = Provenance::new(ValueSource::Default, None, &json!("normal"), sentence);
+        assert_eq!(default.confidence, 0.5);
+    }
+}
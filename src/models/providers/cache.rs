@@ -0,0 +1,412 @@
+// src/models/providers/cache.rs - Caches model responses by rendered prompt and model settings
+
+use super::stream::{FinishReason, StreamEvent, TokenStream};
+use super::tools::{ToolCall, ToolDefinition};
+use super::{ModelConfig, ModelProvider};
+use crate::metrics;
+use crate::utils::fingerprint::fingerprint;
+use crate::utils::lru::Lru;
+use crate::workflow::trace::{self, TraceEvent};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+fn default_capacity() -> usize {
+    1000
+}
+
+fn default_ttl_secs() -> u64 {
+    24 * 3600
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Entries kept in memory, least recently used ones are evicted first
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Directory of the on-disk store, which survives restarts
+    #[serde(default)]
+    pub disk_path: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: default_capacity(),
+            ttl_secs: default_ttl_secs(),
+            disk_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    response: String,
+    /// Seconds since the Unix epoch
+    created_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Response store shared by every cached provider: an in-memory LRU in front
+/// of an optional directory of JSON files. Entries expire after `ttl_secs`.
+#[derive(Debug)]
+pub struct ResponseCache {
+    config: CacheConfig,
+    memory: Mutex<Lru<CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            memory: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Key of a call. The rendered prompt embeds the template and the catalog
+    /// entries it was built from, so editing either yields new keys and stale
+    /// responses are never served. A SHA-256 of the call, so two prompts
+    /// never share an entry, in memory or on disk.
+    pub fn key(provider: &str, model_name: &str, model: &ModelConfig, prompt: &str) -> String {
+        fingerprint(&[
+            provider,
+            model_name,
+            &format!("{:.3}", model.temperature),
+            // Responses cut at a smaller budget must not answer larger ones
+            &model.max_tokens.to_string(),
+            model.system.as_deref().unwrap_or_default(),
+            &serde_json::to_string(&model.output).unwrap_or_default(),
+            prompt,
+        ])
+    }
+
+    fn is_fresh(&self, entry: &CachedResponse) -> bool {
+        now_secs().saturating_sub(entry.created_at) < self.config.ttl_secs
+    }
+
+    fn disk_file(&self, key: &str) -> Option<PathBuf> {
+        self.config
+            .disk_path
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", key)))
+    }
+
+    pub async fn get(&self, key: &str) -> Option<String> {
+        let cached = self.memory.lock().unwrap().get(key);
+        if let Some(entry) = cached {
+            if self.is_fresh(&entry) {
+                return Some(entry.response);
+            }
+            self.memory.lock().unwrap().remove(key);
+        }
+
+        let file = self.disk_file(key)?;
+        let content = tokio::fs::read_to_string(&file).await.ok()?;
+        let entry: CachedResponse = serde_json::from_str(&content).ok()?;
+        if !self.is_fresh(&entry) {
+            let _ = tokio::fs::remove_file(&file).await;
+            return None;
+        }
+
+        let response = entry.response.clone();
+        self.memory
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), entry, self.config.capacity);
+        Some(response)
+    }
+
+    pub async fn put(&self, key: &str, response: &str) {
+        let entry = CachedResponse {
+            response: response.to_string(),
+            created_at: now_secs(),
+        };
+
+        if let Some(file) = self.disk_file(key) {
+            // The cache is an optimization, failing to persist it isn't an error
+            if let Err(e) = write_entry(&file, &entry).await {
+                warn!("Failed to write cache entry {}: {}", file.display(), e);
+            }
+        }
+
+        self.memory
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), entry, self.config.capacity);
+    }
+}
+
+async fn write_entry(
+    file: &std::path::Path,
+    entry: &CachedResponse,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(dir) = file.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(file, serde_json::to_string(entry)?).await?;
+    Ok(())
+}
+
+/// Serves repeated calls from the shared [`ResponseCache`]
+pub struct CachingProvider {
+    name: String,
+    inner: Arc<dyn ModelProvider>,
+    cache: Arc<ResponseCache>,
+}
+
+impl CachingProvider {
+    pub fn new(name: &str, inner: Arc<dyn ModelProvider>, cache: Arc<ResponseCache>) -> Self {
+        Self {
+            name: name.to_string(),
+            inner,
+            cache,
+        }
+    }
+
+    fn key(&self, prompt: &str, model: &ModelConfig) -> String {
+        ResponseCache::key(&self.name, &self.inner.model_name(model), model, prompt)
+    }
+
+    // The cached response of a call, counting the hit or miss
+    async fn lookup(&self, key: &str, model: &ModelConfig) -> Option<String> {
+        let model_name = self.inner.model_name(model);
+        let labels = [("provider", self.name.as_str())];
+        let response = self.cache.get(key).await;
+        let hit = response.is_some();
+        if hit {
+            debug!("Cache hit for {} ({})", self.name, model_name);
+        }
+        let counter = if hit {
+            "llm_cache_hits"
+        } else {
+            "llm_cache_misses"
+        };
+        metrics::increment(&metrics::labeled(counter, &labels), 1);
+        trace::record(TraceEvent::CacheLookup {
+            provider: self.name.clone(),
+            model: model_name,
+            hit,
+        });
+        response
+    }
+}
+
+#[async_trait]
+impl ModelProvider for CachingProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let key = self.key(prompt, model);
+        if let Some(response) = self.lookup(&key, model).await {
+            return Ok(response);
+        }
+
+        let response = self.inner.generate(prompt, model).await?;
+        self.cache.put(&key, &response).await;
+        Ok(response)
+    }
+
+    // Hits are replayed as a single chunk; misses are stored once their stream
+    // ends, unless it failed, was cut at `max_tokens` or was dropped before
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        let key = self.key(prompt, model);
+        if let Some(response) = self.lookup(&key, model).await {
+            return Ok(Box::pin(stream::once(async move {
+                Ok(StreamEvent::Text(response))
+            })));
+        }
+
+        let tokens = self.inner.generate_stream(prompt, model).await?;
+        let cache = self.cache.clone();
+        let cached = stream::unfold(Some((tokens, Some(String::new()))), move |state| {
+            let cache = cache.clone();
+            let key = key.clone();
+            async move {
+                let (mut tokens, mut response) = state?;
+                match tokens.next().await {
+                    Some(Ok(event)) => {
+                        match (&event, response.as_mut()) {
+                            (StreamEvent::Text(text), Some(response)) => response.push_str(text),
+                            (StreamEvent::Finished(FinishReason::Length), _) => response = None,
+                            _ => {}
+                        }
+                        Some((Ok(event), Some((tokens, response))))
+                    }
+                    Some(Err(error)) => Some((Err(error), None)),
+                    None => {
+                        cache.put(&key, &response?).await;
+                        None
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(cached))
+    }
+
+    // Tool calls are not cached, their arguments depend on the tools offered
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        self.inner.call_tool(prompt, model, tools).await
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.inner.model_name(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::stream::collect;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingProvider(AtomicUsize);
+
+    #[async_trait]
+    impl ModelProvider for CountingProvider {
+        async fn generate(
+            &self,
+            prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("{} #{}", prompt, n))
+        }
+    }
+
+    fn model() -> ModelConfig {
+        ModelConfig {
+            ollama: "llama2".to_string(),
+            temperature: 0.1,
+            max_tokens: 100,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hits_misses_and_key() {
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let cache = Arc::new(ResponseCache::new(CacheConfig {
+            enabled: true,
+            ..Default::default()
+        }));
+        let provider = CachingProvider::new("cache-test", live.clone(), cache);
+
+        let trace = trace::ExecutionTrace::default();
+        trace
+            .scope(async {
+                assert_eq!(provider.generate("a", &model()).await.unwrap(), "a #0");
+                assert_eq!(provider.generate("a", &model()).await.unwrap(), "a #0");
+            })
+            .await;
+        let hits: Vec<bool> = trace
+            .entries()
+            .into_iter()
+            .filter_map(|entry| match entry.event {
+                TraceEvent::CacheLookup { hit, .. } => Some(hit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(hits, vec![false, true]);
+        assert_eq!(
+            metrics::snapshot().get(r#"llm_cache_hits{provider="cache-test"}"#),
+            Some(&1)
+        );
+
+        // An edited template or a different temperature is a different call
+        assert_eq!(provider.generate("a'", &model()).await.unwrap(), "a' #1");
+        let hotter = ModelConfig {
+            temperature: 0.7,
+            ..model()
+        };
+        assert_eq!(provider.generate("a", &hotter).await.unwrap(), "a #2");
+        assert_eq!(live.0.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_responses_are_cached() {
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let cache = Arc::new(ResponseCache::new(CacheConfig {
+            enabled: true,
+            ..Default::default()
+        }));
+        let provider = CachingProvider::new("cache-stream-test", live.clone(), cache);
+
+        let tokens = provider.generate_stream("a", &model()).await.unwrap();
+        let streamed = collect(tokens).await.unwrap();
+        assert_eq!(streamed.text, "a #0");
+
+        let tokens = provider.generate_stream("a", &model()).await.unwrap();
+        assert_eq!(collect(tokens).await.unwrap().text, "a #0");
+        assert_eq!(provider.generate("a", &model()).await.unwrap(), "a #0");
+        assert_eq!(live.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction_and_ttl() {
+        let cache = ResponseCache::new(CacheConfig {
+            enabled: true,
+            capacity: 2,
+            ..Default::default()
+        });
+        cache.put("a", "1").await;
+        cache.put("b", "2").await;
+        assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+        // "b" is now the least recently used entry
+        cache.put("c", "3").await;
+        assert_eq!(cache.get("b").await, None);
+        assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+        assert_eq!(cache.get("c").await.as_deref(), Some("3"));
+
+        let expired = ResponseCache::new(CacheConfig {
+            ttl_secs: 0,
+            ..Default::default()
+        });
+        expired.put("a", "1").await;
+        assert_eq!(expired.get("a").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("semantic-cache-{}", std::process::id()));
+        let config = CacheConfig {
+            enabled: true,
+            disk_path: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        ResponseCache::new(config.clone())
+            .put("key", "cached")
+            .await;
+        let restarted = ResponseCache::new(config);
+        assert_eq!(restarted.get("key").await.as_deref(), Some("cached"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
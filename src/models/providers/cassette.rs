@@ -0,0 +1,602 @@
+// src/models/providers/cassette.rs - Record/replay of model calls for deterministic offline runs
+
+use super::error::ProviderError;
+use super::stream::{FinishReason, StreamEvent, TokenStream};
+use super::tools::{ToolCall, ToolDefinition};
+use super::{ModelConfig, ModelProvider};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// One recorded model call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub provider: String,
+    pub prompt: String,
+    pub model: ModelConfig,
+    /// Tools offered to the model, empty for text generations
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+    pub response: String,
+    /// Tool the model called, when tools were offered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call: Option<ToolCall>,
+    /// Set when the response was cut at `max_tokens`; `response` is then the
+    /// text produced until the cut
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<Truncation>,
+}
+
+/// Where a recorded response was cut
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Truncation {
+    pub model: String,
+    pub max_tokens: u32,
+}
+
+impl Interaction {
+    // The call, without its response yet
+    fn new(provider: &str, prompt: &str, model: &ModelConfig, tools: &[ToolDefinition]) -> Self {
+        Self {
+            provider: provider.to_string(),
+            prompt: prompt.to_string(),
+            model: model.clone(),
+            tools: tools.to_vec(),
+            response: String::new(),
+            tool_call: None,
+            truncated: None,
+        }
+    }
+
+    fn matches(&self, other: &Interaction) -> bool {
+        self.provider == other.provider
+            && self.prompt == other.prompt
+            && self.model == other.model
+            && self.tools == other.tools
+    }
+}
+
+/// Recorded interactions, persisted as JSON so diffs stay reviewable
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A cassette file shared by every provider of a run
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl Cassette {
+    /// Loads the cassette, starting empty when the file doesn't exist yet
+    pub async fn open(path: impl AsRef<Path>) -> Result<Arc<Self>, Box<dyn Error + Send + Sync>> {
+        let path = path.as_ref().to_path_buf();
+        let interactions = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str::<CassetteFile>(&content)?.interactions,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        info!(
+            "Opened cassette {} with {} interactions",
+            path.display(),
+            interactions.len()
+        );
+
+        Ok(Arc::new(Self {
+            path,
+            interactions: Mutex::new(interactions),
+        }))
+    }
+
+    // The recording of the same call as `call`, whose response is ignored
+    async fn find(&self, call: &Interaction) -> Option<Interaction> {
+        self.interactions
+            .lock()
+            .await
+            .iter()
+            .find(|i| i.matches(call))
+            .cloned()
+    }
+
+    // Stores the interaction, replacing an earlier recording of the same call,
+    // and rewrites the file so a crashed run keeps what it recorded
+    async fn record(&self, interaction: Interaction) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut interactions = self.interactions.lock().await;
+        interactions.retain(|i| !i.matches(&interaction));
+        interactions.push(interaction);
+
+        let file = CassetteFile {
+            interactions: interactions.clone(),
+        };
+        tokio::fs::write(&self.path, serde_json::to_string_pretty(&file)?).await?;
+        Ok(())
+    }
+}
+
+/// Forwards calls to a live provider and records every response
+pub struct RecordingProvider {
+    name: String,
+    inner: Arc<dyn ModelProvider>,
+    cassette: Arc<Cassette>,
+}
+
+impl RecordingProvider {
+    pub fn new(name: &str, inner: Arc<dyn ModelProvider>, cassette: Arc<Cassette>) -> Self {
+        Self {
+            name: name.to_string(),
+            inner,
+            cassette,
+        }
+    }
+}
+
+#[async_trait]
+impl ModelProvider for RecordingProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        // Cut responses are recorded as well, so the retries or continuations
+        // that follow them can be replayed
+        let interaction = match self.inner.generate(prompt, model).await {
+            Ok(response) => Interaction {
+                response,
+                ..Interaction::new(&self.name, prompt, model, &[])
+            },
+            Err(error) => {
+                let Some(ProviderError::Truncated {
+                    model: model_name,
+                    max_tokens,
+                    partial,
+                }) = error.downcast_ref::<ProviderError>()
+                else {
+                    return Err(error);
+                };
+                debug!("Recording truncated {} response to cassette", self.name);
+                self.cassette
+                    .record(Interaction {
+                        response: partial.clone(),
+                        truncated: Some(Truncation {
+                            model: model_name.clone(),
+                            max_tokens: *max_tokens,
+                        }),
+                        ..Interaction::new(&self.name, prompt, model, &[])
+                    })
+                    .await?;
+                return Err(error);
+            }
+        };
+
+        debug!("Recording {} response to cassette", self.name);
+        let response = interaction.response.clone();
+        self.cassette.record(interaction).await?;
+
+        Ok(response)
+    }
+
+    // Recorded once the stream ends, unless it failed or was dropped before its
+    // end, like `generate` records only answers
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        let tokens = self.inner.generate_stream(prompt, model).await?;
+        let interaction = Interaction::new(&self.name, prompt, model, &[]);
+        let truncation = Truncation {
+            model: self.inner.model_name(model),
+            max_tokens: model.max_tokens,
+        };
+        let cassette = self.cassette.clone();
+
+        let recorded = stream::unfold(Some((tokens, interaction)), move |state| {
+            let cassette = cassette.clone();
+            let truncation = truncation.clone();
+            async move {
+                let (mut tokens, mut interaction) = state?;
+                match tokens.next().await {
+                    Some(Ok(event)) => {
+                        match &event {
+                            StreamEvent::Text(text) => interaction.response.push_str(text),
+                            StreamEvent::Finished(FinishReason::Length) => {
+                                interaction.truncated = Some(truncation)
+                            }
+                            _ => {}
+                        }
+                        Some((Ok(event), Some((tokens, interaction))))
+                    }
+                    Some(Err(error)) => Some((Err(error), None)),
+                    None => {
+                        debug!(
+                            "Recording {} streamed response to cassette",
+                            interaction.provider
+                        );
+                        match cassette.record(interaction).await {
+                            Ok(()) => None,
+                            Err(error) => Some((Err(error), None)),
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(recorded))
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let call = self.inner.call_tool(prompt, model, tools).await?;
+
+        debug!("Recording {} tool call to cassette", self.name);
+        self.cassette
+            .record(Interaction {
+                tool_call: call.clone(),
+                ..Interaction::new(&self.name, prompt, model, tools)
+            })
+            .await?;
+
+        Ok(call)
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.inner.model_name(model)
+    }
+}
+
+/// Serves recorded responses. Unknown calls fail in strict mode; otherwise they
+/// go to the live provider (when there is one) and are added to the cassette.
+pub struct ReplayProvider {
+    name: String,
+    fallback: Option<Arc<dyn ModelProvider>>,
+    cassette: Arc<Cassette>,
+    strict: bool,
+}
+
+impl ReplayProvider {
+    pub fn new(
+        name: &str,
+        fallback: Option<Arc<dyn ModelProvider>>,
+        cassette: Arc<Cassette>,
+        strict: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            fallback,
+            cassette,
+            strict,
+        }
+    }
+
+    // The live provider to ask for a call missing from the cassette
+    fn live(&self) -> Result<RecordingProvider, Box<dyn Error + Send + Sync>> {
+        match &self.fallback {
+            Some(fallback) if !self.strict => {
+                warn!(
+                    "Prompt not found in cassette, calling live {} provider",
+                    self.name
+                );
+                Ok(RecordingProvider::new(
+                    &self.name,
+                    fallback.clone(),
+                    self.cassette.clone(),
+                ))
+            }
+            _ => Err(format!(
+                "No recorded {} response for this prompt in cassette {}",
+                self.name,
+                self.cassette.path.display()
+            )
+            .into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ModelProvider for ReplayProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let call = Interaction::new(&self.name, prompt, model, &[]);
+        if let Some(recorded) = self.cassette.find(&call).await {
+            debug!("Replaying {} response from cassette", self.name);
+            return match recorded.truncated {
+                Some(truncation) => Err(ProviderError::Truncated {
+                    model: truncation.model,
+                    max_tokens: truncation.max_tokens,
+                    partial: recorded.response,
+                }
+                .into()),
+                None => Ok(recorded.response),
+            };
+        }
+
+        self.live()?.generate(prompt, model).await
+    }
+
+    // Recorded responses are replayed as a single chunk, followed by the
+    // end at `max_tokens` of the ones that were cut
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        let call = Interaction::new(&self.name, prompt, model, &[]);
+        if let Some(recorded) = self.cassette.find(&call).await {
+            debug!("Replaying {} response from cassette", self.name);
+            let mut events = vec![Ok(StreamEvent::Text(recorded.response))];
+            if recorded.truncated.is_some() {
+                events.push(Ok(StreamEvent::Finished(FinishReason::Length)));
+            }
+            return Ok(Box::pin(stream::iter(events)));
+        }
+
+        self.live()?.generate_stream(prompt, model).await
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let call = Interaction::new(&self.name, prompt, model, tools);
+        if let Some(recorded) = self.cassette.find(&call).await {
+            debug!("Replaying {} tool call from cassette", self.name);
+            return Ok(recorded.tool_call);
+        }
+
+        self.live()?.call_tool(prompt, model, tools).await
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        match &self.fallback {
+            Some(fallback) => fallback.model_name(model),
+            None => model.name.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::stream::collect;
+    use crate::models::providers::truncation::{TruncationConfig, TruncationGuard};
+    use crate::workflow::trace::{ExecutionTrace, TraceEvent};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingProvider(AtomicUsize);
+
+    #[async_trait]
+    impl ModelProvider for CountingProvider {
+        async fn generate(
+            &self,
+            prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("{} #{}", prompt, n))
+        }
+
+        async fn call_tool(
+            &self,
+            prompt: &str,
+            _model: &ModelConfig,
+            tools: &[ToolDefinition],
+        ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(ToolCall {
+                name: tools[0].name.clone(),
+                arguments: serde_json::json!({"prompt": prompt, "n": n}),
+            }))
+        }
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "semantic-cassette-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_strict() {
+        let path = cassette_path("strict");
+        let model = ModelConfig {
+            ollama: "llama2".to_string(),
+            temperature: 0.1,
+            max_tokens: 100,
+            ..Default::default()
+        };
+
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let recorder = RecordingProvider::new("ollama", live, Cassette::open(&path).await.unwrap());
+        assert_eq!(
+            recorder.generate("hello", &model).await.unwrap(),
+            "hello #0"
+        );
+
+        // A fresh run reads the file back and never needs the live provider
+        let replay =
+            ReplayProvider::new("ollama", None, Cassette::open(&path).await.unwrap(), true);
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #0");
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #0");
+
+        // Different prompt, model settings or provider are not the same call
+        assert!(replay.generate("bye", &model).await.is_err());
+        let hotter = ModelConfig {
+            temperature: 0.9,
+            ..model.clone()
+        };
+        assert!(replay.generate("hello", &hotter).await.is_err());
+        let other = ReplayProvider::new("claude", None, Cassette::open(&path).await.unwrap(), true);
+        assert!(other.generate("hello", &model).await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_tool_calls() {
+        let path = cassette_path("tools");
+        let model = ModelConfig::default();
+        let tools = vec![ToolDefinition {
+            name: "send_email".to_string(),
+            description: "Send an email".to_string(),
+            parameters: serde_json::json!({"type": "object"}),
+        }];
+
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let recorder = RecordingProvider::new("claude", live, Cassette::open(&path).await.unwrap());
+        let recorded = recorder.call_tool("hello", &model, &tools).await.unwrap();
+        assert_eq!(
+            recorder.generate("hello", &model).await.unwrap(),
+            "hello #1"
+        );
+
+        let replay =
+            ReplayProvider::new("claude", None, Cassette::open(&path).await.unwrap(), true);
+        assert_eq!(
+            replay.call_tool("hello", &model, &tools).await.unwrap(),
+            recorded
+        );
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #1");
+        // Other tools are another call
+        let renamed = vec![ToolDefinition {
+            name: "send_sms".to_string(),
+            ..tools[0].clone()
+        }];
+        assert!(replay.call_tool("hello", &model, &renamed).await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_streams() {
+        let path = cassette_path("streams");
+        let model = ModelConfig::default();
+
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let recorder = RecordingProvider::new("ollama", live, Cassette::open(&path).await.unwrap());
+        let tokens = recorder.generate_stream("hello", &model).await.unwrap();
+        // Nothing is recorded before the stream ends
+        assert!(Cassette::open(&path)
+            .await
+            .unwrap()
+            .interactions
+            .lock()
+            .await
+            .is_empty());
+        let streamed = collect(tokens).await.unwrap();
+        assert_eq!(streamed.text, "hello #0");
+
+        let replay =
+            ReplayProvider::new("ollama", None, Cassette::open(&path).await.unwrap(), true);
+        let tokens = replay.generate_stream("hello", &model).await.unwrap();
+        let replayed = collect(tokens).await.unwrap();
+        assert_eq!(replayed.text, "hello #0");
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #0");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    // Cuts answers at fewer than 200 tokens, like a model with a long answer
+    struct LongAnswerProvider;
+
+    #[async_trait]
+    impl ModelProvider for LongAnswerProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            if model.max_tokens < 200 {
+                return Err(ProviderError::Truncated {
+                    model: "llama2".to_string(),
+                    max_tokens: model.max_tokens,
+                    partial: "{\"to\": \"al".to_string(),
+                }
+                .into());
+            }
+            Ok("{\"to\": \"alice@example.com\"}".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_truncated_calls() {
+        let path = cassette_path("truncated");
+        let model = ModelConfig {
+            max_tokens: 100,
+            ..Default::default()
+        };
+
+        let recorder = Arc::new(RecordingProvider::new(
+            "ollama",
+            Arc::new(LongAnswerProvider),
+            Cassette::open(&path).await.unwrap(),
+        ));
+        let guard = TruncationGuard::new(recorder, TruncationConfig::default());
+        let recorded = guard.generate("email alice", &model).await.unwrap();
+
+        // Replayed offline, the first call is cut again and the guard retries
+        let replay = Arc::new(ReplayProvider::new(
+            "ollama",
+            None,
+            Cassette::open(&path).await.unwrap(),
+            true,
+        ));
+        let guard = TruncationGuard::new(replay, TruncationConfig::default());
+        let trace = ExecutionTrace::default();
+        let replayed = trace
+            .scope(guard.generate("email alice", &model))
+            .await
+            .unwrap();
+        assert_eq!(replayed, recorded);
+        assert!(matches!(
+            trace.entries()[0].event,
+            TraceEvent::Truncated {
+                max_tokens: 100,
+                ..
+            }
+        ));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_replay_records_misses() {
+        let path = cassette_path("lenient");
+        let model = ModelConfig::default();
+        let live = Arc::new(CountingProvider(AtomicUsize::new(0)));
+
+        let replay = ReplayProvider::new(
+            "ollama",
+            Some(live.clone()),
+            Cassette::open(&path).await.unwrap(),
+            false,
+        );
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #0");
+        assert_eq!(replay.generate("hello", &model).await.unwrap(), "hello #0");
+        assert_eq!(live.0.load(Ordering::SeqCst), 1);
+
+        let strict =
+            ReplayProvider::new("ollama", None, Cassette::open(&path).await.unwrap(), true);
+        assert_eq!(strict.generate("hello", &model).await.unwrap(), "hello #0");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
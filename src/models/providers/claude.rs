@@ -1,42 +1,186 @@
-// src/models/providers/claude.rs - Update ClaudeProvider
+// src/models/providers/claude.rs - Provider for the Anthropic Messages API
 
-use super::{ModelConfig, ModelProvider, ProviderConfig, ProviderSelector};
+use super::error::{self, ApiErrorKind, ProviderError};
+use super::http::HttpClient;
+use super::stream::{self, FinishReason, StreamEvent, TokenStream, Usage};
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage as token_usage;
+use super::{ModelConfig, ModelProvider, OutputFormat, ProviderConfig, ProviderSelector};
 use async_trait::async_trait;
+use futures::stream as futures_stream;
+use futures::StreamExt;
 use serde::Serialize;
+use serde_json::{json, Value};
 use std::error::Error;
-use tracing::{debug, info};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+
+// Name of the tool Claude is made to call when the output must match a schema
+const OUTPUT_TOOL: &str = "output";
 
 pub struct ClaudeProvider {
+    client: HttpClient,
     api_key: String,
+    /// Default model of the instance
+    model: Option<String>,
+    base_url: String,
 }
 
 #[derive(Serialize)]
-struct ClaudeRequest {
+struct ClaudeRequest<'a> {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
     temperature: f32,
     max_tokens: u32,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
 }
 
 #[derive(Serialize)]
-struct Message {
-    role: String,
-    content: String,
+struct Tool<'a> {
+    name: &'a str,
+    description: &'a str,
+    input_schema: &'a Value,
+}
+
+fn finish_reason(stop_reason: &str) -> FinishReason {
+    match stop_reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolUse,
+        "refusal" => FinishReason::Refusal,
+        _ => FinishReason::Other,
+    }
+}
+
+fn usage(value: &Value) -> Usage {
+    Usage {
+        input_tokens: value["input_tokens"].as_u64().unwrap_or(0) as u32,
+        output_tokens: value["output_tokens"].as_u64().unwrap_or(0) as u32,
+    }
+}
+
+// Typed error of an API error body, `{"type": "error", "error": {"type", "message"}}`
+fn api_error(
+    status: Option<u16>,
+    body: &Value,
+    retry_after: Option<Duration>,
+) -> Option<ProviderError> {
+    let error = body.get("error")?;
+    Some(ProviderError::Api {
+        provider: "Claude".to_string(),
+        kind: ApiErrorKind::from_claude(error["type"].as_str()?),
+        status,
+        message: error["message"].as_str().unwrap_or_default().to_string(),
+        retry_after,
+    })
+}
+
+// Events of a server-sent event line. Text comes from text deltas, or from the
+// JSON deltas of the output tool; usage from message_start and message_delta.
+fn parse_event(line: &str) -> Result<Vec<StreamEvent>, Box<dyn Error + Send + Sync>> {
+    let Some(data) = line.strip_prefix("data:") else {
+        return Ok(vec![]);
+    };
+    let event: Value = serde_json::from_str(data.trim())?;
+
+    let events = match event["type"].as_str() {
+        Some("message_start") => vec![StreamEvent::Usage(usage(&event["message"]["usage"]))],
+        Some("content_block_delta") => {
+            let delta = &event["delta"];
+            let text = match delta["type"].as_str() {
+                Some("input_json_delta") => delta["partial_json"].as_str(),
+                _ => delta["text"].as_str(),
+            };
+            text.filter(|text| !text.is_empty())
+                .map(|text| StreamEvent::Text(text.to_string()))
+                .into_iter()
+                .collect()
+        }
+        Some("message_delta") => {
+            let mut events = vec![StreamEvent::Usage(usage(&event["usage"]))];
+            if let Some(stop_reason) = event["delta"]["stop_reason"].as_str() {
+                events.push(StreamEvent::Finished(finish_reason(stop_reason)));
+            }
+            events
+        }
+        Some("error") => {
+            return Err(match api_error(None, &event, None) {
+                Some(error) => error.into(),
+                None => format!("Claude API error: {}", data.trim()).into(),
+            })
+        }
+        _ => vec![],
+    };
+    Ok(events)
 }
 
 impl ClaudeProvider {
-    pub fn new(config: &ProviderConfig) -> Self {
+    pub fn new(config: &ProviderConfig) -> Result<Self, Box<dyn Error + Send + Sync>> {
         // Check if enabled to keep compiler happy
         if !config.enabled {
             debug!("Creating Claude provider, but it's disabled in config");
         }
 
-        Self {
+        Ok(Self {
+            client: HttpClient::new(config.name_or("claude"), &config.http)?,
             api_key: config
                 .api_key
                 .clone()
-                .expect("Claude API key not specified"),
+                .ok_or("Claude API key not specified")?,
+            model: config.model.clone(),
+            base_url: config
+                .base_url
+                .as_deref()
+                .unwrap_or(DEFAULT_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+        })
+    }
+
+    async fn send(
+        &self,
+        request: &ClaudeRequest<'_>,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let request = self
+            .client
+            .post(&format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(request);
+        let response = self.client.send(request).await?;
+
+        // 529 (overloaded) and 429 (rate limited) come back as JSON errors
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let retry_after = error::retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            let error = serde_json::from_str(&body)
+                .ok()
+                .and_then(|value| api_error(Some(status), &value, retry_after))
+                .unwrap_or(ProviderError::Http {
+                    provider: "Claude".to_string(),
+                    status,
+                    body,
+                    retry_after,
+                });
+            return Err(error.into());
         }
+
+        Ok(response)
     }
 }
 
@@ -47,43 +191,337 @@ impl ModelProvider for ClaudeProvider {
         prompt: &str,
         config: &ModelConfig,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let generation = stream::collect(self.generate_stream(prompt, config).await?).await?;
+        token_usage::record(
+            &self.model_name(config),
+            prompt,
+            config,
+            &generation.text,
+            generation.usage,
+        );
+
+        let usage = generation.usage.unwrap_or_default();
+        info!(
+            "Claude finished ({:?}) with {} input and {} output tokens",
+            generation.finish_reason, usage.input_tokens, usage.output_tokens
+        );
+        if generation.finish_reason == Some(FinishReason::Length) {
+            warn!(
+                "Claude response was cut at max_tokens ({})",
+                config.max_tokens
+            );
+            return Err(ProviderError::Truncated {
+                model: self.model_name(config),
+                max_tokens: config.max_tokens,
+                partial: generation.text,
+            }
+            .into());
+        }
+
+        if generation.text.trim().is_empty() {
+            return Err(ProviderError::EmptyResponse {
+                provider: "Claude".to_string(),
+            }
+            .into());
+        }
+
+        Ok(generation.text)
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
         debug!("Generating response with Claude API");
 
-        // Get the appropriate Claude model name
-        let model_name = ProviderSelector::get_model_name(config, true);
+        // Schema-constrained output is produced by forcing a call of a tool
+        // whose input schema is the output schema; its input is the answer
+        let (tools, tool_choice) = match &config.output {
+            Some(OutputFormat::Schema(schema)) => (
+                vec![Tool {
+                    name: OUTPUT_TOOL,
+                    description: "Records the answer",
+                    input_schema: schema,
+                }],
+                Some(json!({"type": "tool", "name": OUTPUT_TOOL})),
+            ),
+            _ => (vec![], None),
+        };
 
         let request = ClaudeRequest {
-            model: model_name,
+            model: self.model_name(config),
             messages: vec![Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
+                role: "user",
+                content: prompt,
             }],
+            system: config.system.as_deref(),
             temperature: config.temperature,
             max_tokens: config.max_tokens,
+            stream: true,
+            tools,
+            tool_choice,
         };
+        let response = self.send(&request).await?;
 
-        let client = reqwest::Client::new();
-        let response = client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await?;
+        let events = stream::lines(response).flat_map(|line| {
+            let events = match line.and_then(|line| parse_event(&line)) {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures_stream::iter(events)
+        });
+        Ok(Box::pin(events))
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let request = ClaudeRequest {
+            model: self.model_name(config),
+            messages: vec![Message {
+                role: "user",
+                content: prompt,
+            }],
+            system: config.system.as_deref(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            stream: false,
+            tools: tools
+                .iter()
+                .map(|tool| Tool {
+                    name: &tool.name,
+                    description: &tool.description,
+                    input_schema: &tool.parameters,
+                })
+                .collect(),
+            // Any of the tools, but one of them
+            tool_choice: Some(json!({"type": "any"})),
+        };
+
+        let response: Value = self.send(&request).await?.json().await?;
+        token_usage::record(
+            &request.model,
+            prompt,
+            config,
+            &response["content"].to_string(),
+            Some(usage(&response["usage"])),
+        );
+        // A tool call cut at max_tokens has incomplete arguments
+        if response["stop_reason"] == "max_tokens" {
+            warn!(
+                "Claude tool call was cut at max_tokens ({})",
+                config.max_tokens
+            );
+            return Err(ProviderError::Truncated {
+                model: request.model,
+                max_tokens: config.max_tokens,
+                partial: response["content"].to_string(),
+            }
+            .into());
+        }
+        let call = response["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|block| block["type"] == "tool_use")
+            .map(|block| ToolCall {
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+            });
+        Ok(call)
+    }
+
+    fn model_name(&self, config: &ModelConfig) -> String {
+        match &self.model {
+            Some(model) if config.claude.is_empty() => model.clone(),
+            _ => ProviderSelector::get_model_name(config, true),
+        }
+    }
+}
 
-        let response_json: serde_json::Value = response.json().await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::test_server::{streaming_server, stub_server};
+
+    fn provider(base_url: &str) -> ClaudeProvider {
+        ClaudeProvider::new(&ProviderConfig {
+            enabled: true,
+            api_key: Some("secret".to_string()),
+            base_url: Some(base_url.to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn model_config() -> ModelConfig {
+        ModelConfig {
+            claude: "claude-3-haiku".to_string(),
+            temperature: 0.1,
+            max_tokens: 100,
+            ..Default::default()
+        }
+    }
 
-        println!(
-            "Claude API response: {}",
-            serde_json::to_string_pretty(&response_json).unwrap()
+    fn request_body(request: &str) -> Value {
+        serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_streams_text_stop_reason_and_usage() {
+        let (address, request) = streaming_server(
+            "text/event-stream",
+            &[
+                "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,",
+                "\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\nevent: ping\ndata: {\"type\":\"ping\"}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" world\"}}\n\n",
+                "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"},\"usage\":{\"output_tokens\":100}}\n\n",
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            ],
+        )
+        .await;
+
+        let model = ModelConfig {
+            system: Some("You are terse".to_string()),
+            ..model_config()
+        };
+        let tokens = provider(&address)
+            .generate_stream("hello", &model)
+            .await
+            .unwrap();
+        let generation = stream::collect(tokens).await.unwrap();
+        assert_eq!(generation.text, "Hello world");
+        assert_eq!(generation.finish_reason, Some(FinishReason::Length));
+        assert_eq!(
+            generation.usage,
+            Some(Usage {
+                input_tokens: 25,
+                output_tokens: 100
+            })
         );
 
-        let content = response_json["content"][0]["text"]
-            .as_str()
-            .ok_or("Invalid response format")?
-            .to_string();
+        let request = request.await.unwrap();
+        assert!(request.contains("x-api-key: secret"));
+        let body = request_body(&request);
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["system"], "You are terse");
+        assert!(body.get("tools").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_schema_output_through_forced_tool_call() {
+        let (address, request) = streaming_server(
+            "text/event-stream",
+            &[
+                "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"tool_use\",\"name\":\"output\",\"input\":{}}}\n\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"to\\\": \"}}\n\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"Alice\\\"}\"}}\n\n",
+                "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":12}}\n\n",
+            ],
+        )
+        .await;
+
+        let schema = json!({"type": "object", "properties": {"to": {"type": "string"}}});
+        let model = model_config().with_output(OutputFormat::Schema(schema.clone()));
+        let response = provider(&address).generate("hello", &model).await.unwrap();
+        assert_eq!(response, r#"{"to": "Alice"}"#);
+
+        let body = request_body(&request.await.unwrap());
+        assert_eq!(body["tools"][0]["input_schema"], schema);
+        assert_eq!(
+            body["tool_choice"],
+            json!({"type": "tool", "name": "output"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_call_tool() {
+        let (address, request) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"content":[{"type":"text","text":"Sending it"},{"type":"tool_use","id":"t1","name":"send_email","input":{"to":"alice@example.com"}}],"stop_reason":"tool_use"}"#,
+        )
+        .await;
 
-        info!("Successfully received response from Claude API");
-        Ok(content)
+        let tools = vec![ToolDefinition {
+            name: "send_email".to_string(),
+            description: "Send an email".to_string(),
+            parameters: json!({"type": "object", "properties": {"to": {"type": "string"}}}),
+        }];
+        let call = provider(&address)
+            .call_tool("email alice@example.com", &model_config(), &tools)
+            .await
+            .unwrap();
+        assert_eq!(
+            call,
+            Some(ToolCall {
+                name: "send_email".to_string(),
+                arguments: json!({"to": "alice@example.com"}),
+            })
+        );
+
+        let body = request_body(&request.await.unwrap());
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["tools"][0]["name"], "send_email");
+        assert_eq!(body["tool_choice"], json!({"type": "any"}));
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_cut_at_max_tokens() {
+        let (address, _request) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"content":[{"type":"tool_use","id":"t1","name":"send_email","input":{}}],"stop_reason":"max_tokens"}"#,
+        )
+        .await;
+
+        let error = provider(&address)
+            .call_tool("email alice@example.com", &model_config(), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ProviderError>(),
+            Some(ProviderError::Truncated {
+                max_tokens: 100,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_typed_api_errors() {
+        let (address, _request) = stub_server(
+            "529 Site Overloaded",
+            "application/json",
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        )
+        .await;
+        let error = provider(&address)
+            .generate("hello", &model_config())
+            .await
+            .unwrap_err();
+        match error.downcast_ref::<ProviderError>() {
+            Some(ProviderError::Api { kind, status, .. }) => {
+                assert_eq!(*kind, ApiErrorKind::Overloaded);
+                assert_eq!(*status, Some(529));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // Errors can also arrive in the middle of a stream
+        let error = parse_event(
+            r#"data: {"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long"}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Claude API error (InvalidRequest): prompt is too long"
+        );
+        assert!(parse_event("event: ping").unwrap().is_empty());
     }
 }
@@ -0,0 +1,153 @@
+// src/models/providers/error.rs - Errors providers report so callers can react to them
+
+use std::fmt;
+use std::time::Duration;
+
+/// Category of an error reported in an API error body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    /// Malformed request, e.g. too many tokens or an invalid schema
+    InvalidRequest,
+    /// Missing, invalid or unauthorized API key
+    Authentication,
+    RateLimited,
+    /// The provider is temporarily over capacity
+    Overloaded,
+    /// Internal error of the provider
+    Server,
+    Other(String),
+}
+
+impl ApiErrorKind {
+    /// Maps the `error.type` of an Anthropic API error body
+    pub fn from_claude(error_type: &str) -> Self {
+        match error_type {
+            "invalid_request_error" | "not_found_error" | "request_too_large" => {
+                ApiErrorKind::InvalidRequest
+            }
+            "authentication_error" | "permission_error" => ApiErrorKind::Authentication,
+            "rate_limit_error" => ApiErrorKind::RateLimited,
+            "overloaded_error" => ApiErrorKind::Overloaded,
+            "api_error" => ApiErrorKind::Server,
+            other => ApiErrorKind::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The server answered with a non-success HTTP status
+    Http {
+        provider: String,
+        status: u16,
+        body: String,
+        /// Delay the server asked for in a `Retry-After` header
+        retry_after: Option<Duration>,
+    },
+    /// The server reported an error with a typed body, either as the HTTP
+    /// response or in the middle of a stream (no `status` then)
+    Api {
+        provider: String,
+        kind: ApiErrorKind,
+        status: Option<u16>,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// The server answered successfully but without any text
+    EmptyResponse { provider: String },
+    /// The provider or model can't call tools
+    ToolsUnsupported { model: String },
+    /// Generation stopped at `max_tokens`; `partial` is the text produced so far
+    Truncated {
+        model: String,
+        max_tokens: u32,
+        partial: String,
+    },
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::Http {
+                provider,
+                status,
+                body,
+                ..
+            } => write!(f, "{} request failed: {} {}", provider, status, body),
+            ProviderError::Api {
+                provider,
+                kind,
+                status,
+                message,
+                ..
+            } => {
+                write!(f, "{} API error ({:?}", provider, kind)?;
+                if let Some(status) = status {
+                    write!(f, ", {}", status)?;
+                }
+                write!(f, "): {}", message)
+            }
+            ProviderError::EmptyResponse { provider } => {
+                write!(f, "Empty response from {}", provider)
+            }
+            ProviderError::ToolsUnsupported { model } => {
+                write!(f, "{} does not support tool calling", model)
+            }
+            ProviderError::Truncated {
+                model, max_tokens, ..
+            } => write!(
+                f,
+                "Output of {} was cut at max_tokens ({}), raise max_tokens or enable continuation",
+                model, max_tokens
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl ProviderError {
+    /// Whether the server refused the call because of its rate limits
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            ProviderError::Http { status, .. } => *status == 429,
+            ProviderError::Api { kind, .. } => *kind == ApiErrorKind::RateLimited,
+            _ => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ProviderError::Http { retry_after, .. } | ProviderError::Api { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Delay of a `Retry-After` header given in seconds; HTTP dates are ignored
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    let seconds: f64 = value.trim().parse().ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+}
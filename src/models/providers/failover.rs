@@ -0,0 +1,364 @@
+// src/models/providers/failover.rs - Ordered chain of providers tried until one answers
+
+use super::error::{ApiErrorKind, ProviderError};
+use super::stream::{StreamEvent, TokenStream};
+use super::tools::{ToolCall, ToolDefinition};
+use super::{ModelConfig, ModelProvider};
+use crate::workflow::trace::{self, FailoverReason, TraceEvent};
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::error::Error;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Tries each provider in order, moving to the next one on errors that another
+/// provider may not have (unreachable host, timeout, 429/5xx, empty response).
+/// Other errors, such as a rejected request, are returned as-is.
+pub struct FailoverProvider {
+    chain: Vec<(String, Arc<dyn ModelProvider>)>,
+}
+
+impl FailoverProvider {
+    pub fn new(chain: Vec<(String, Arc<dyn ModelProvider>)>) -> Self {
+        Self { chain }
+    }
+
+    fn answered(name: &str, model_name: String) {
+        info!("Response provided by {} ({})", name, model_name);
+        trace::record(TraceEvent::ProviderAnswered {
+            provider: name.to_string(),
+            model: model_name,
+        });
+    }
+
+    // Returns the error to keep for the next provider, or as `Err` the error
+    // to give up with when failing over wouldn't help
+    fn failed(
+        name: &str,
+        model_name: String,
+        error: Box<dyn Error + Send + Sync>,
+    ) -> Result<Box<dyn Error + Send + Sync>, Box<dyn Error + Send + Sync>> {
+        let Some(reason) = classify_error(error.as_ref()) else {
+            return Err(error);
+        };
+        warn!(
+            "Provider {} failed ({:?}), trying the next one: {}",
+            name, reason, error
+        );
+        trace::record(TraceEvent::ProviderFailed {
+            provider: name.to_string(),
+            model: model_name,
+            reason,
+            error: error.to_string(),
+        });
+        Ok(error)
+    }
+
+    fn exhausted(last_error: Option<Box<dyn Error + Send + Sync>>) -> Box<dyn Error + Send + Sync> {
+        match last_error {
+            Some(error) => format!("All providers of the failover chain failed: {}", error).into(),
+            None => "Failover chain is empty".into(),
+        }
+    }
+}
+
+/// Classifies an error into a failover reason, `None` when failing over wouldn't help
+pub fn classify_error(error: &(dyn Error + 'static)) -> Option<FailoverReason> {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<ProviderError>() {
+            return match error {
+                ProviderError::EmptyResponse { .. } => Some(FailoverReason::EmptyResponse),
+                // The next provider would most likely be cut as well
+                ProviderError::ToolsUnsupported { .. } | ProviderError::Truncated { .. } => None,
+                ProviderError::Http { status, .. } => classify_status(*status),
+                ProviderError::Api { kind, .. } => match kind {
+                    ApiErrorKind::RateLimited => Some(FailoverReason::RateLimited),
+                    ApiErrorKind::Overloaded | ApiErrorKind::Server => {
+                        Some(FailoverReason::ServerError)
+                    }
+                    // Like other 4xx statuses, client errors are not failed over
+                    ApiErrorKind::InvalidRequest
+                    | ApiErrorKind::Authentication
+                    | ApiErrorKind::Other(_) => None,
+                },
+            };
+        }
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            if error.is_timeout() {
+                return Some(FailoverReason::Timeout);
+            }
+            if error.is_connect() {
+                return Some(FailoverReason::ConnectionRefused);
+            }
+            if let Some(status) = error.status() {
+                return classify_status(status.as_u16());
+            }
+        }
+        if error.is::<tokio::time::error::Elapsed>() {
+            return Some(FailoverReason::Timeout);
+        }
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            match error.kind() {
+                std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset => {
+                    return Some(FailoverReason::ConnectionRefused)
+                }
+                std::io::ErrorKind::TimedOut => return Some(FailoverReason::Timeout),
+                _ => {}
+            }
+        }
+        current = error.source();
+    }
+    None
+}
+
+fn classify_status(status: u16) -> Option<FailoverReason> {
+    match status {
+        408 => Some(FailoverReason::Timeout),
+        429 => Some(FailoverReason::RateLimited),
+        500..=599 => Some(FailoverReason::ServerError),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl ModelProvider for FailoverProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut last_error = None;
+
+        for (name, provider) in &self.chain {
+            let model_name = provider.model_name(model);
+            match provider.generate(prompt, model).await {
+                Ok(text) if text.trim().is_empty() => {
+                    let error = ProviderError::EmptyResponse {
+                        provider: name.clone(),
+                    };
+                    last_error = Some(Self::failed(name, model_name, error.into())?);
+                }
+                Ok(text) => {
+                    Self::answered(name, model_name);
+                    return Ok(text);
+                }
+                Err(error) => last_error = Some(Self::failed(name, model_name, error)?),
+            }
+        }
+
+        Err(Self::exhausted(last_error))
+    }
+
+    // A provider is committed to once its first text arrives; until then, errors
+    // and empty answers move on to the next one like they do for `generate`
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        let mut last_error = None;
+
+        for (name, provider) in &self.chain {
+            let model_name = provider.model_name(model);
+            let mut tokens = match provider.generate_stream(prompt, model).await {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    last_error = Some(Self::failed(name, model_name, error)?);
+                    continue;
+                }
+            };
+
+            let mut received = Vec::new();
+            let first_text = loop {
+                match tokens.next().await {
+                    Some(Ok(StreamEvent::Text(text))) if !text.trim().is_empty() => break Ok(text),
+                    Some(Ok(event)) => received.push(Ok(event)),
+                    Some(Err(error)) => break Err(error),
+                    None => {
+                        break Err(ProviderError::EmptyResponse {
+                            provider: name.clone(),
+                        }
+                        .into())
+                    }
+                }
+            };
+            match first_text {
+                Ok(text) => {
+                    Self::answered(name, model_name);
+                    received.push(Ok(StreamEvent::Text(text)));
+                    return Ok(Box::pin(futures::stream::iter(received).chain(tokens)));
+                }
+                Err(error) => last_error = Some(Self::failed(name, model_name, error)?),
+            }
+        }
+
+        Err(Self::exhausted(last_error))
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let mut last_error = None;
+
+        for (name, provider) in &self.chain {
+            let model_name = provider.model_name(model);
+            match provider.call_tool(prompt, model, tools).await {
+                Ok(call) => {
+                    Self::answered(name, model_name);
+                    return Ok(call);
+                }
+                Err(error) => last_error = Some(Self::failed(name, model_name, error)?),
+            }
+        }
+
+        Err(Self::exhausted(last_error))
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.chain
+            .first()
+            .map(|(_, provider)| provider.model_name(model))
+            .unwrap_or_else(|| model.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::ollama::OllamaProvider;
+    use crate::models::providers::stream;
+    use crate::models::providers::ProviderConfig;
+    use crate::workflow::trace::ExecutionTrace;
+
+    enum Behavior {
+        Answer(&'static str),
+        Status(u16),
+    }
+
+    struct StubProvider(Behavior);
+
+    #[async_trait]
+    impl ModelProvider for StubProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            match self.0 {
+                Behavior::Answer(text) => Ok(text.to_string()),
+                Behavior::Status(status) => Err(ProviderError::Http {
+                    provider: "Stub".to_string(),
+                    status,
+                    body: String::new(),
+                    retry_after: None,
+                }
+                .into()),
+            }
+        }
+    }
+
+    fn stub(name: &str, behavior: Behavior) -> (String, Arc<dyn ModelProvider>) {
+        (name.to_string(), Arc::new(StubProvider(behavior)))
+    }
+
+    // An Ollama provider pointing at a port nothing listens on
+    async fn unreachable_ollama() -> (String, Arc<dyn ModelProvider>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(host),
+            ..Default::default()
+        })
+        .unwrap();
+        ("ollama".to_string(), Arc::new(provider))
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_classified_errors() {
+        let chain = FailoverProvider::new(vec![
+            stub("claude", Behavior::Status(529)),
+            unreachable_ollama().await,
+            stub("empty", Behavior::Answer("  ")),
+            stub("openai", Behavior::Answer("{}")),
+        ]);
+
+        let trace = ExecutionTrace::default();
+        let response = trace
+            .scope(chain.generate("hello", &ModelConfig::default()))
+            .await
+            .unwrap();
+        assert_eq!(response, "{}");
+
+        let reasons: Vec<_> = trace
+            .entries()
+            .into_iter()
+            .map(|entry| match entry.event {
+                TraceEvent::ProviderFailed {
+                    provider, reason, ..
+                } => format!("{}: {:?}", provider, reason),
+                TraceEvent::ProviderAnswered { provider, .. } => format!("{}: answered", provider),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "claude: ServerError",
+                "ollama: ConnectionRefused",
+                "empty: EmptyResponse",
+                "openai: answered"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failover_of_streams_before_their_first_text() {
+        let chain = FailoverProvider::new(vec![
+            stub("claude", Behavior::Status(529)),
+            stub("empty", Behavior::Answer("  ")),
+            stub("openai", Behavior::Answer("{}")),
+        ]);
+
+        let trace = ExecutionTrace::default();
+        let generation = trace
+            .scope(async {
+                let tokens = chain
+                    .generate_stream("hello", &ModelConfig::default())
+                    .await?;
+                stream::collect(tokens).await
+            })
+            .await
+            .unwrap();
+        assert_eq!(generation.text, "{}");
+        assert!(matches!(
+            trace.entries().last().unwrap().event,
+            TraceEvent::ProviderAnswered { ref provider, .. } if provider == "openai"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_no_failover_on_client_errors() {
+        let chain = FailoverProvider::new(vec![
+            stub("claude", Behavior::Status(400)),
+            stub("ollama", Behavior::Answer("{}")),
+        ]);
+        let error = chain
+            .generate("hello", &ModelConfig::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("400"));
+
+        let exhausted = FailoverProvider::new(vec![stub("claude", Behavior::Status(429))]);
+        let error = exhausted
+            .generate("hello", &ModelConfig::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("All providers"));
+    }
+}
@@ -0,0 +1,319 @@
+// src/models/providers/hedged.rs - Hedged requests: duplicate slow calls to cut tail latency
+
+use super::stream::TokenStream;
+use super::tools::{ToolCall, ToolDefinition};
+use super::{ModelConfig, ModelProvider};
+use crate::workflow::trace::{self, HedgeWinner, TraceEvent};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+// Number of recent latencies the hedge delay is computed from
+const WINDOW_SIZE: usize = 100;
+
+fn default_percentile() -> f64 {
+    95.0
+}
+
+fn default_initial_delay_ms() -> u64 {
+    2000
+}
+
+fn default_min_delay_ms() -> u64 {
+    100
+}
+
+fn default_min_samples() -> usize {
+    10
+}
+
+/// When to fire a duplicate request, and where to send it
+#[derive(Debug, Deserialize, Clone)]
+pub struct HedgeConfig {
+    /// Latency percentile of recent calls after which the duplicate is sent
+    #[serde(default = "default_percentile")]
+    pub percentile: f64,
+    /// Delay used until enough latencies were observed
+    #[serde(default = "default_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+    /// Lower bound of the delay, so fast backends aren't hit twice for every call
+    #[serde(default = "default_min_delay_ms")]
+    pub min_delay_ms: u64,
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+    /// Provider the duplicate goes to, defaults to the step's provider
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self {
+            percentile: default_percentile(),
+            initial_delay_ms: default_initial_delay_ms(),
+            min_delay_ms: default_min_delay_ms(),
+            min_samples: default_min_samples(),
+            provider: None,
+        }
+    }
+}
+
+/// Recent latencies of one provider and model, shared by every request
+#[derive(Debug, Default)]
+pub struct LatencyWindow {
+    samples: Mutex<VecDeque<u64>>,
+}
+
+impl LatencyWindow {
+    pub fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == WINDOW_SIZE {
+            samples.pop_front();
+        }
+        samples.push_back(latency.as_millis() as u64);
+    }
+
+    /// Nearest-rank percentile, `None` until `min_samples` latencies were recorded
+    pub fn percentile(&self, percentile: f64, min_samples: usize) -> Option<u64> {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() || samples.len() < min_samples {
+            return None;
+        }
+        let mut sorted: Vec<u64> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+}
+
+/// Sends the request to `primary` and, when it hasn't answered after the hedge
+/// delay, a duplicate to `secondary`. The first successful response wins and
+/// the other request is dropped, which cancels it.
+pub struct HedgedProvider {
+    primary: Arc<dyn ModelProvider>,
+    secondary: Arc<dyn ModelProvider>,
+    latencies: Arc<LatencyWindow>,
+    config: HedgeConfig,
+}
+
+impl HedgedProvider {
+    pub fn new(
+        primary: Arc<dyn ModelProvider>,
+        secondary: Arc<dyn ModelProvider>,
+        latencies: Arc<LatencyWindow>,
+        config: HedgeConfig,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            latencies,
+            config,
+        }
+    }
+
+    fn hedge_delay(&self) -> Duration {
+        let delay = self
+            .latencies
+            .percentile(self.config.percentile, self.config.min_samples)
+            .unwrap_or(self.config.initial_delay_ms)
+            .max(self.config.min_delay_ms);
+        Duration::from_millis(delay)
+    }
+}
+
+#[async_trait]
+impl ModelProvider for HedgedProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let delay = self.hedge_delay();
+        let started = Instant::now();
+        let primary = self.primary.generate(prompt, model);
+        tokio::pin!(primary);
+
+        tokio::select! {
+            result = &mut primary => {
+                if result.is_ok() {
+                    self.latencies.record(started.elapsed());
+                }
+                return result;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        debug!(
+            "No response after {} ms, sending a hedged request",
+            delay.as_millis()
+        );
+        let hedge_started = Instant::now();
+        let hedge = self.secondary.generate(prompt, model);
+        tokio::pin!(hedge);
+
+        // Whichever succeeds first wins; an error only counts once both are done
+        let (result, winner) = tokio::select! {
+            result = &mut primary => match result {
+                Ok(text) => (Ok(text), HedgeWinner::Primary),
+                Err(_) => (hedge.await, HedgeWinner::Hedge),
+            },
+            result = &mut hedge => match result {
+                Ok(text) => (Ok(text), HedgeWinner::Hedge),
+                Err(_) => (primary.await, HedgeWinner::Primary),
+            },
+        };
+
+        if result.is_ok() {
+            let latency = match winner {
+                HedgeWinner::Primary => started.elapsed(),
+                HedgeWinner::Hedge => hedge_started.elapsed(),
+            };
+            self.latencies.record(latency);
+        }
+        trace::record(TraceEvent::Hedged {
+            delay_ms: delay.as_millis() as u64,
+            winner,
+        });
+
+        result
+    }
+
+    // Streams are not hedged: the first chunk, not the whole answer, is what
+    // a stream's caller waits for
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        self.primary.generate_stream(prompt, model).await
+    }
+
+    // Tool calls are not hedged
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        self.primary.call_tool(prompt, model, tools).await
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.primary.model_name(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Answers after `delays[n]` ms on its n-th call and counts completed calls
+    struct SlowProvider {
+        delays: Vec<u64>,
+        calls: AtomicUsize,
+        completed: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ModelProvider for SlowProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(self.delays[call])).await;
+            self.completed.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("call {}", call))
+        }
+    }
+
+    fn slow_provider(delays: Vec<u64>) -> (Arc<SlowProvider>, Arc<AtomicUsize>) {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let provider = Arc::new(SlowProvider {
+            delays,
+            calls: AtomicUsize::new(0),
+            completed: completed.clone(),
+        });
+        (provider, completed)
+    }
+
+    fn hedge_config() -> HedgeConfig {
+        HedgeConfig {
+            initial_delay_ms: 50,
+            min_delay_ms: 10,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hedge_wins_and_loser_is_cancelled() {
+        let (provider, completed) = slow_provider(vec![1000, 10]);
+        let hedged = HedgedProvider::new(
+            provider.clone(),
+            provider,
+            Arc::new(LatencyWindow::default()),
+            hedge_config(),
+        );
+
+        let trace = trace::ExecutionTrace::default();
+        let response = trace
+            .scope(hedged.generate("hello", &ModelConfig::default()))
+            .await
+            .unwrap();
+        assert_eq!(response, "call 1");
+        assert_eq!(
+            trace.entries()[0].event,
+            TraceEvent::Hedged {
+                delay_ms: 50,
+                winner: HedgeWinner::Hedge
+            }
+        );
+
+        // The slow first request was dropped and never completes
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fast_response_is_not_hedged() {
+        let (provider, completed) = slow_provider(vec![5]);
+        let latencies = Arc::new(LatencyWindow::default());
+        let hedged = HedgedProvider::new(
+            provider.clone(),
+            provider,
+            latencies.clone(),
+            hedge_config(),
+        );
+
+        let response = hedged
+            .generate("hello", &ModelConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(response, "call 0");
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+        assert_eq!(latencies.percentile(50.0, 1).map(|ms| ms < 50), Some(true));
+    }
+
+    #[test]
+    fn test_latency_percentile() {
+        let window = LatencyWindow::default();
+        assert_eq!(window.percentile(95.0, 1), None);
+        for ms in 1..=100 {
+            window.record(Duration::from_millis(ms));
+        }
+        assert_eq!(window.percentile(95.0, 10), Some(95));
+        assert_eq!(window.percentile(50.0, 10), Some(50));
+        assert_eq!(window.percentile(95.0, 200), None);
+
+        // Only the most recent samples are kept
+        window.record(Duration::from_millis(1000));
+        assert_eq!(window.percentile(100.0, 10), Some(1000));
+        assert_eq!(window.percentile(1.0, 10), Some(2));
+    }
+}
@@ -0,0 +1,399 @@
+// src/models/providers/host_pool.rs - Ollama hosts a provider balances its requests over
+
+use super::http::HttpClient;
+use crate::metrics;
+use serde::Deserialize;
+use serde_json::Value;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+// Health checks don't wait as long as model calls
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How requests are spread over the hosts
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Balancing {
+    /// The host with the fewest requests in flight
+    #[default]
+    LeastOutstanding,
+    RoundRobin,
+}
+
+fn default_health_check_interval_secs() -> u64 {
+    15
+}
+
+fn default_max_failures() -> u32 {
+    3
+}
+
+fn default_ejection_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HostPoolConfig {
+    #[serde(default)]
+    pub balancing: Balancing,
+    /// Period of the `/api/tags` checks of every host, 0 disables them
+    #[serde(default = "default_health_check_interval_secs")]
+    pub health_check_interval_secs: u64,
+    /// Consecutive failed calls that eject a host
+    #[serde(default = "default_max_failures")]
+    pub max_failures: u32,
+    /// How long an ejected host gets no requests, unless a check finds it healthy first
+    #[serde(default = "default_ejection_secs")]
+    pub ejection_secs: u64,
+}
+
+impl Default for HostPoolConfig {
+    fn default() -> Self {
+        Self {
+            balancing: Balancing::default(),
+            health_check_interval_secs: default_health_check_interval_secs(),
+            max_failures: default_max_failures(),
+            ejection_secs: default_ejection_secs(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Health {
+    consecutive_failures: u32,
+    ejected_until: Option<Instant>,
+    /// Models the last check found on the host, unknown before the first one
+    models: Option<Vec<String>>,
+}
+
+impl Health {
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.ejected_until.is_some_and(|until| until > now)
+    }
+}
+
+struct Host {
+    url: String,
+    outstanding: AtomicUsize,
+    health: Mutex<Health>,
+}
+
+impl Host {
+    fn serves(&self, model: &str) -> bool {
+        match &self.health.lock().unwrap().models {
+            // `llama2` is the `llama2:latest` of the tags
+            Some(models) => models
+                .iter()
+                .any(|name| name == model || name.strip_suffix(":latest") == Some(model)),
+            None => true,
+        }
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.health.lock().unwrap().is_ejected(now)
+    }
+}
+
+pub struct HostPool {
+    hosts: Vec<Host>,
+    config: HostPoolConfig,
+    next: AtomicUsize,
+}
+
+/// A host picked for one request. It counts as outstanding until dropped,
+/// so streams keep their lease until they end.
+pub struct HostLease {
+    pool: Arc<HostPool>,
+    index: usize,
+}
+
+impl HostLease {
+    pub fn url(&self) -> &str {
+        &self.pool.hosts[self.index].url
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn succeeded(&self) {
+        let mut health = self.pool.hosts[self.index].health.lock().unwrap();
+        health.consecutive_failures = 0;
+    }
+
+    /// Counts a failed call; enough of them in a row eject the host
+    pub fn failed(&self) {
+        let host = &self.pool.hosts[self.index];
+        let mut health = host.health.lock().unwrap();
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.pool.config.max_failures {
+            warn!(
+                "Ejecting Ollama host {} after {} failed calls",
+                host.url, health.consecutive_failures
+            );
+            health.consecutive_failures = 0;
+            health.ejected_until =
+                Some(Instant::now() + Duration::from_secs(self.pool.config.ejection_secs));
+            metrics::increment(
+                &metrics::labeled("ollama_host_ejections", &[("host", &host.url)]),
+                1,
+            );
+        }
+    }
+}
+
+impl Drop for HostLease {
+    fn drop(&mut self) {
+        self.pool.hosts[self.index]
+            .outstanding
+            .fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl HostPool {
+    pub fn new(urls: Vec<String>, config: HostPoolConfig) -> Self {
+        let hosts = urls
+            .into_iter()
+            .map(|url| Host {
+                url: url.trim_end_matches('/').to_string(),
+                outstanding: AtomicUsize::new(0),
+                health: Mutex::new(Health::default()),
+            })
+            .collect();
+        Self {
+            hosts,
+            config,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Picks a host serving `model` among those not `tried` yet, preferring
+    /// hosts that aren't ejected. `None` when no such host is left.
+    pub fn acquire(self: &Arc<Self>, model: &str, tried: &[usize]) -> Option<HostLease> {
+        let now = Instant::now();
+        let candidates: Vec<usize> = (0..self.hosts.len())
+            .filter(|index| !tried.contains(index) && self.hosts[*index].serves(model))
+            .collect();
+        let healthy: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|index| !self.hosts[*index].is_ejected(now))
+            .collect();
+        // With every host ejected, trying one beats failing right away
+        let eligible = if healthy.is_empty() {
+            candidates
+        } else {
+            healthy
+        };
+        if eligible.is_empty() {
+            return None;
+        }
+
+        // Rotating the start spreads ties between equally loaded hosts
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % eligible.len();
+        let rotated = eligible[start..].iter().chain(&eligible[..start]);
+        let index = match self.config.balancing {
+            Balancing::RoundRobin => eligible[start],
+            Balancing::LeastOutstanding => *rotated
+                .min_by_key(|index| self.hosts[**index].outstanding.load(Ordering::SeqCst))
+                .unwrap(),
+        };
+
+        let host = &self.hosts[index];
+        host.outstanding.fetch_add(1, Ordering::SeqCst);
+        metrics::increment(
+            &metrics::labeled("ollama_host_requests", &[("host", &host.url)]),
+            1,
+        );
+        Some(HostLease {
+            pool: self.clone(),
+            index,
+        })
+    }
+
+    /// Hosts that answered the last check, and those of them that have `model`
+    pub fn availability(&self, model: &str) -> (usize, usize) {
+        let now = Instant::now();
+        let reachable: Vec<&Host> = self
+            .hosts
+            .iter()
+            .filter(|host| {
+                let health = host.health.lock().unwrap();
+                health.models.is_some() && !health.is_ejected(now)
+            })
+            .collect();
+        let serving = reachable.iter().filter(|host| host.serves(model)).count();
+        (reachable.len(), serving)
+    }
+
+    /// Lists the models of every host. Hosts that don't answer are ejected;
+    /// ejected hosts that answer are brought back.
+    pub async fn check(&self, client: &HttpClient) {
+        for host in &self.hosts {
+            let request = client
+                .get(&format!("{}/api/tags", host.url))
+                .timeout(CHECK_TIMEOUT);
+            match list_models(client, request).await {
+                Ok(models) => {
+                    debug!("Ollama host {} serves {:?}", host.url, models);
+                    let mut health = host.health.lock().unwrap();
+                    if health.ejected_until.take().is_some() {
+                        info!("Ollama host {} is healthy again", host.url);
+                        metrics::increment(
+                            &metrics::labeled("ollama_host_recoveries", &[("host", &host.url)]),
+                            1,
+                        );
+                    }
+                    health.consecutive_failures = 0;
+                    health.models = Some(models);
+                }
+                Err(e) => {
+                    let now = Instant::now();
+                    let mut health = host.health.lock().unwrap();
+                    if !health.is_ejected(now) {
+                        warn!("Ejecting Ollama host {}: {}", host.url, e);
+                        metrics::increment(
+                            &metrics::labeled("ollama_host_ejections", &[("host", &host.url)]),
+                            1,
+                        );
+                    }
+                    // Kept out at least until the next check
+                    let secs = self
+                        .config
+                        .ejection_secs
+                        .max(self.config.health_check_interval_secs);
+                    health.ejected_until = Some(now + Duration::from_secs(secs));
+                }
+            }
+        }
+    }
+
+    /// Checks the hosts now and then periodically, for as long as the pool
+    /// exists. A single host has nothing to balance, so it isn't checked.
+    pub fn spawn_health_checks(self: &Arc<Self>, client: HttpClient) {
+        let interval = self.config.health_check_interval_secs;
+        if self.hosts.len() < 2 || interval == 0 {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("No async runtime, Ollama hosts won't be health-checked");
+            return;
+        };
+
+        let pool: Weak<Self> = Arc::downgrade(self);
+        runtime.spawn(async move {
+            let mut ticks = tokio::time::interval(Duration::from_secs(interval));
+            loop {
+                ticks.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    return;
+                };
+                pool.check(&client).await;
+            }
+        });
+    }
+}
+
+async fn list_models(
+    client: &HttpClient,
+    request: reqwest::RequestBuilder,
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let tags: Value = client
+        .send(request)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(tags["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["name"].as_str().map(str::to_string))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::http::HttpConfig;
+    use crate::models::providers::test_server::stub_server;
+
+    fn pool(hosts: usize, balancing: Balancing) -> Arc<HostPool> {
+        let urls = (0..hosts).map(|i| format!("http://ollama-{}:11434", i));
+        Arc::new(HostPool::new(
+            urls.collect(),
+            HostPoolConfig {
+                balancing,
+                ..Default::default()
+            },
+        ))
+    }
+
+    #[test]
+    fn test_least_outstanding_and_round_robin() {
+        let least = pool(3, Balancing::LeastOutstanding);
+        let first = least.acquire("llama2", &[]).unwrap();
+        let second = least.acquire("llama2", &[]).unwrap();
+        let third = least.acquire("llama2", &[]).unwrap();
+        let mut indices = vec![first.index(), second.index(), third.index()];
+        indices.sort();
+        assert_eq!(indices, vec![0, 1, 2]);
+
+        // The freed host is picked, the others still have a request in flight
+        let freed = second.index();
+        drop(second);
+        assert_eq!(least.acquire("llama2", &[]).unwrap().index(), freed);
+
+        let round_robin = pool(2, Balancing::RoundRobin);
+        let picks: Vec<usize> = (0..4)
+            .map(|_| round_robin.acquire("llama2", &[]).unwrap().index())
+            .collect();
+        assert_eq!(picks, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_failing_host_is_ejected() {
+        let pool = pool(2, Balancing::RoundRobin);
+        for _ in 0..3 {
+            pool.acquire("llama2", &[1]).unwrap().failed();
+        }
+        let picks: Vec<usize> = (0..3)
+            .map(|_| pool.acquire("llama2", &[]).unwrap().index())
+            .collect();
+        assert_eq!(picks, vec![1, 1, 1]);
+
+        // Excluding the healthy host leaves the ejected one rather than nothing
+        assert_eq!(pool.acquire("llama2", &[1]).unwrap().index(), 0);
+        assert!(pool.acquire("llama2", &[0, 1]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_lists_models_and_ejects_unreachable_hosts() {
+        let (serving, _) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"models":[{"name":"llama2:latest"},{"name":"mistral:7b"}]}"#,
+        )
+        .await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let pool = Arc::new(HostPool::new(
+            vec![unreachable, serving],
+            HostPoolConfig::default(),
+        ));
+        let client = HttpClient::new("ollama-check-test", &HttpConfig::default()).unwrap();
+        pool.check(&client).await;
+
+        assert!(pool.hosts[0].is_ejected(Instant::now()));
+        assert_eq!(pool.acquire("llama2", &[]).unwrap().index(), 1);
+        assert_eq!(pool.acquire("mistral:7b", &[]).unwrap().index(), 1);
+        // Host 1 lacks the model and host 0 is still unknown, but ejected
+        assert_eq!(pool.acquire("phi3", &[]).unwrap().index(), 0);
+        assert!(pool.acquire("phi3", &[0]).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+// src/models/providers/http.rs - Long-lived HTTP client of a provider, with its timeouts, pool and metrics
+
+use crate::metrics;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_read_timeout_secs() -> u64 {
+    120
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    8
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+/// Connection settings of a provider's HTTP client
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpConfig {
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next bytes of a response. Streamed responses can
+    /// run longer as long as tokens keep coming.
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Limit on a whole call, unset by default
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// Proxy for all requests, e.g. `http://proxy.internal:3128`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// PEM file of a CA trusted on top of the system roots
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    /// Speak HTTP/2 without negotiating it first (h2c to vLLM, gateways, ...)
+    #[serde(default)]
+    pub http2: bool,
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            request_timeout_secs: None,
+            proxy: None,
+            ca_cert: None,
+            http2: false,
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+        }
+    }
+}
+
+/// Client a provider keeps for its lifetime, so calls share pooled
+/// connections and TLS sessions. Counts per provider:
+/// - `http_requests` and `http_request_ms`, the time to the response headers
+/// - `http_request_errors` and `http_timeouts`
+/// - `http_connections` opened; the other requests reused a pooled one
+#[derive(Clone)]
+pub struct HttpClient {
+    provider: String,
+    client: reqwest::Client,
+}
+
+impl HttpClient {
+    pub fn new(provider: &str, config: &HttpConfig) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let connections = metrics::labeled("http_connections", &[("provider", provider)]);
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(config.read_timeout_secs))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+            // The connector is only asked for a connection when the pool has none
+            .connector_layer(tower::util::MapRequestLayer::new(move |uri| {
+                metrics::increment(&connections, 1);
+                uri
+            }));
+
+        if let Some(secs) = config.request_timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(|e| format!("Invalid proxy for {}: {}", provider, e))?,
+            );
+        }
+        if let Some(path) = &config.ca_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Cannot read CA certificate {}: {}", path.display(), e))?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if config.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        debug!("HTTP client for {}: {:?}", provider, config);
+        Ok(Self {
+            provider: provider.to_string(),
+            client: builder.build()?,
+        })
+    }
+
+    pub fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends a request built by [`HttpClient::post`] or [`HttpClient::get`]
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let labels = [("provider", self.provider.as_str())];
+        let started = Instant::now();
+        let result = request.send().await;
+
+        metrics::increment(&metrics::labeled("http_requests", &labels), 1);
+        metrics::increment(
+            &metrics::labeled("http_request_ms", &labels),
+            started.elapsed().as_millis() as u64,
+        );
+        if let Err(e) = &result {
+            metrics::increment(&metrics::labeled("http_request_errors", &labels), 1);
+            if e.is_timeout() {
+                metrics::increment(&metrics::labeled("http_timeouts", &labels), 1);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    // Answers every request on a connection with `{}` and keeps it open
+    async fn keep_alive_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut socket = BufReader::new(socket);
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if socket.read_line(&mut line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            socket
+                                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}")
+                                .await
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_requests_reuse_pooled_connections() {
+        let address = keep_alive_server().await;
+        let client = HttpClient::new("http-test-reuse", &HttpConfig::default()).unwrap();
+
+        for _ in 0..3 {
+            let response = client.send(client.post(&address)).await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "{}");
+        }
+
+        let counters = metrics::snapshot();
+        let labels = [("provider", "http-test-reuse")];
+        assert_eq!(counters[&metrics::labeled("http_requests", &labels)], 3);
+        assert_eq!(counters[&metrics::labeled("http_connections", &labels)], 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        // Accepts connections and never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = HttpConfig {
+            read_timeout_secs: 1,
+            ..Default::default()
+        };
+        let client = HttpClient::new("http-test-timeout", &config).unwrap();
+        let error = client.send(client.post(&address)).await.unwrap_err();
+        assert!(error.is_timeout());
+
+        let labels = [("provider", "http-test-timeout")];
+        assert_eq!(
+            metrics::snapshot()[&metrics::labeled("http_timeouts", &labels)],
+            1
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let config = HttpConfig {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        };
+        let error = HttpClient::new("http-test", &config).err().unwrap();
+        assert!(error.to_string().contains("/nonexistent/ca.pem"));
+    }
+}
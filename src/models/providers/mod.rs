@@ -1,13 +1,35 @@
 // src/models/providers/mod.rs - Update ModelConfig struct
 
 use async_trait::async_trait;
-use serde::Deserialize;
+use futures::stream as futures_stream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+pub mod cache;
+pub mod cassette;
 pub mod claude;
+pub mod error;
+pub mod failover;
+pub mod hedged;
+pub mod host_pool;
+pub mod http;
 pub mod ollama;
+pub mod openai;
+pub mod rate_limit;
+mod registry;
 mod selector;
+pub mod stream;
+#[cfg(test)]
+pub mod test_server;
+pub mod tools;
+pub mod truncation;
+pub mod usage;
 
+pub use registry::ProviderRegistry;
 pub use selector::ProviderSelector;
 
 #[async_trait]
@@ -17,20 +39,210 @@ pub trait ModelProvider: Send + Sync {
         prompt: &str,
         model: &ModelConfig,
     ) -> Result<String, Box<dyn Error + Send + Sync>>;
+
+    /// Streams the response as it is generated. Providers without a streaming
+    /// API yield the complete response as a single chunk.
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<stream::TokenStream, Box<dyn Error + Send + Sync>> {
+        let response = self.generate(prompt, model).await?;
+        Ok(Box::pin(futures_stream::once(async move {
+            Ok(stream::StreamEvent::Text(response))
+        })))
+    }
+
+    /// Asks the model to call one of `tools`, returning its call, or `None` when
+    /// it answered without calling any. Providers without tool calling fail
+    /// with [`error::ProviderError::ToolsUnsupported`].
+    async fn call_tool(
+        &self,
+        _prompt: &str,
+        model: &ModelConfig,
+        _tools: &[tools::ToolDefinition],
+    ) -> Result<Option<tools::ToolCall>, Box<dyn Error + Send + Sync>> {
+        Err(error::ProviderError::ToolsUnsupported {
+            model: self.model_name(model),
+        }
+        .into())
+    }
+
+    /// Verifies that `model` can be served, e.g. at startup. By default a
+    /// one-token call; providers that can list their models override it.
+    async fn check_model(&self, model: &ModelConfig) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let probe = ModelConfig {
+            max_tokens: 1,
+            system: None,
+            output: None,
+            ..model.clone()
+        };
+        match self.generate("ping", &probe).await {
+            Ok(_) => Ok(()),
+            // A single token is expected to cut the answer
+            Err(e)
+                if matches!(
+                    e.downcast_ref(),
+                    Some(error::ProviderError::Truncated { .. })
+                ) =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Name of the model this provider uses for `model`, as reported in traces
+    fn model_name(&self, model: &ModelConfig) -> String {
+        model.name.clone()
+    }
+
+    /// The provider a failover chain should call in place of this one, `None`
+    /// for this provider itself. A chain has a next member to try, so it
+    /// shouldn't wait out errors the provider would otherwise retry.
+    fn failover_member(&self) -> Option<Arc<dyn ModelProvider>> {
+        None
+    }
+}
+
+/// Wire protocol spoken by a provider
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Ollama,
+    Claude,
+    /// Any server exposing the OpenAI `/v1/chat/completions` API (vLLM, llama.cpp, ...)
+    Openai,
+    /// Ordered chain of other providers, see `chain`
+    Failover,
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct ProviderConfig {
+    /// Instance name, the key of its section; set by [`ProviderConfig::resolve`]
+    #[serde(skip)]
+    pub name: String,
     pub enabled: bool,
+    /// Explicit protocol; inferred from the instance name, then `api_key`/`host`, when missing
+    #[serde(default, rename = "type")]
+    pub kind: Option<ProviderKind>,
     pub host: Option<String>,
+    /// Ollama hosts to balance requests over, instead of the single `host`
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Balancing and health checks of `hosts`
+    #[serde(default)]
+    pub host_pool: host_pool::HostPoolConfig,
     pub api_key: Option<String>,
-    //pub models: ModelsConfig,
+    /// Environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// File holding the API key, e.g. a mounted secret
+    #[serde(default)]
+    pub api_key_file: Option<PathBuf>,
+    /// Model used by steps whose model configuration names none for this provider's type
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Base URL of an OpenAI-compatible server, e.g. `http://localhost:8000/v1`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Extra HTTP headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// The server supports `response_format`, so steps that need JSON ask for it
+    #[serde(default)]
+    pub json_mode: bool,
+    /// Names of the providers a failover provider tries, in order
+    #[serde(default)]
+    pub chain: Vec<String>,
+    /// Use Ollama's `/api/chat` with system and user messages instead of `/api/generate`
+    #[serde(default)]
+    pub chat: bool,
+    /// How long Ollama keeps the model loaded after a call, e.g. `10m`
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Additional Ollama model options (`num_ctx`, `top_p`, `seed`, ...)
+    #[serde(default)]
+    pub options: HashMap<String, serde_json::Value>,
+    /// Request rate, token rate and concurrency limits shared by all workflows
+    #[serde(default)]
+    pub rate_limit: Option<rate_limit::RateLimitConfig>,
+    /// Timeouts, proxy, CA and connection pool of the provider's HTTP client
+    #[serde(default)]
+    pub http: http::HttpConfig,
+}
+
+// Prefix of the environment overrides of an instance: `claude-eu` reads `CLAUDE_EU_API_KEY`
+fn env_prefix(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct ProvidersConfig {}
+impl ProviderConfig {
+    /// The configuration of instance `name` with its environment overrides
+    /// (`<NAME>_HOST`, `<NAME>_BASE_URL`) applied and its API key read from
+    /// `api_key_env`, `api_key_file`, `<NAME>_API_KEY` or `api_key`, in that order
+    pub fn resolve(&self, name: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut config = self.clone();
+        config.name = name.to_string();
+        config.kind = config.kind.or(match name {
+            "ollama" => Some(ProviderKind::Ollama),
+            "claude" => Some(ProviderKind::Claude),
+            "openai" => Some(ProviderKind::Openai),
+            _ => None,
+        });
 
-#[derive(Debug, Deserialize, Clone, Default)]
+        let prefix = env_prefix(name);
+        if let Ok(host) = env::var(format!("{}_HOST", prefix)) {
+            config.host = Some(host);
+        }
+        if let Ok(base_url) = env::var(format!("{}_BASE_URL", prefix)) {
+            config.base_url = Some(base_url);
+        }
+
+        config.api_key =
+            if let Some(var) = &self.api_key_env {
+                Some(env::var(var).map_err(|_| {
+                    format!("{} is not set, provider '{}' has no API key", var, name)
+                })?)
+            } else if let Some(path) = &self.api_key_file {
+                let key = std::fs::read_to_string(path).map_err(|e| {
+                    format!(
+                        "Cannot read API key of provider '{}' from {}: {}",
+                        name,
+                        path.display(),
+                        e
+                    )
+                })?;
+                Some(key.trim().to_string())
+            } else {
+                env::var(format!("{}_API_KEY", prefix))
+                    .ok()
+                    .or_else(|| self.api_key.clone())
+            }
+            .filter(|key| !key.is_empty());
+
+        Ok(config)
+    }
+
+    /// Instance name, `default` for configurations built outside config.yaml
+    pub fn name_or<'a>(&'a self, default: &'a str) -> &'a str {
+        if self.name.is_empty() {
+            default
+        } else {
+            &self.name
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct ModelConfig {
     #[serde(default)]
     pub name: String, // Keep for backward compatibility
@@ -38,28 +250,123 @@ pub struct ModelConfig {
     pub ollama: String,
     #[serde(default)]
     pub claude: String,
+    #[serde(default)]
+    pub openai: String,
     pub temperature: f32,
     pub max_tokens: u32,
+    /// System prompt sent along with the prompt, by providers that support one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Constrained output requested by the step, see [`ModelConfig::with_output`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
 }
 
+/// Shape of the response a step needs. Providers that can constrain their
+/// output do so; the others rely on the prompt's instructions.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Any JSON value
+    Json,
+    /// JSON matching a JSON schema
+    Schema(serde_json::Value),
+}
+
+impl ModelConfig {
+    /// The same model settings, asking for constrained output
+    pub fn with_output(&self, output: OutputFormat) -> Self {
+        Self {
+            output: Some(output),
+            ..self.clone()
+        }
+    }
+}
+
+/// Named model configurations from the `models` section of config.yaml.
+/// Workflow steps refer to them by name (e.g. `sentence_to_json`).
 #[derive(Debug, Deserialize, Clone, Default)]
-pub struct ModelsConfig {
-    pub sentence_to_json: ModelConfig,
-    pub find_endpoint: ModelConfig,
+#[serde(transparent)]
+pub struct ModelsConfig(HashMap<String, ModelConfig>);
+
+impl ModelsConfig {
+    pub fn get(&self, name: &str) -> Result<&ModelConfig, Box<dyn Error + Send + Sync>> {
+        self.0.get(name).ok_or_else(|| {
+            format!("Model configuration '{}' not found in config.yaml", name).into()
+        })
+    }
 }
 
-pub fn create_provider(config: &ProviderConfig) -> Option<Box<dyn ModelProvider>> {
+pub fn create_provider(
+    config: &ProviderConfig,
+) -> Result<Option<Box<dyn ModelProvider>>, Box<dyn Error + Send + Sync>> {
     // We check the enabled flag to avoid warnings about it not being used
     if !config.enabled {
-        return None;
+        return Ok(None);
+    }
+
+    match config.kind {
+        Some(ProviderKind::Ollama) => {
+            return Ok(Some(Box::new(ollama::OllamaProvider::new(config)?)))
+        }
+        Some(ProviderKind::Claude) => {
+            return Ok(Some(Box::new(claude::ClaudeProvider::new(config)?)))
+        }
+        Some(ProviderKind::Openai) => {
+            return Ok(Some(Box::new(openai::OpenAiProvider::new(config)?)))
+        }
+        // Chains refer to other providers by name, see `ProviderRegistry::register_failover`
+        Some(ProviderKind::Failover) => return Ok(None),
+        None => {}
     }
 
     // Use the provider determination logic
-    if config.api_key.is_some() {
-        Some(Box::new(claude::ClaudeProvider::new(config)))
-    } else if config.host.is_some() {
-        Some(Box::new(ollama::OllamaProvider::new(config)))
+    Ok(if config.api_key.is_some() {
+        Some(Box::new(claude::ClaudeProvider::new(config)?))
+    } else if config.host.is_some() || !config.hosts.is_empty() {
+        Some(Box::new(ollama::OllamaProvider::new(config)?))
     } else {
         None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_instance_overrides_and_credentials() {
+        env::set_var("RESOLVE_TEST_EU_API_KEY", "from-env");
+        env::set_var("RESOLVE_TEST_EU_BASE_URL", "https://eu.example.com");
+        let config = ProviderConfig {
+            kind: Some(ProviderKind::Claude),
+            api_key: Some("inline".to_string()),
+            base_url: Some("https://us.example.com".to_string()),
+            ..Default::default()
+        };
+        let resolved = config.resolve("resolve-test-eu").unwrap();
+        assert_eq!(resolved.name, "resolve-test-eu");
+        assert_eq!(resolved.api_key.as_deref(), Some("from-env"));
+        assert_eq!(resolved.base_url.as_deref(), Some("https://eu.example.com"));
+
+        let path = env::temp_dir().join("resolve-test-api-key");
+        std::fs::write(&path, "from-file\n").unwrap();
+        let config = ProviderConfig {
+            api_key_file: Some(path),
+            ..config
+        };
+        let resolved = config.resolve("resolve-test-eu").unwrap();
+        assert_eq!(resolved.api_key.as_deref(), Some("from-file"));
+
+        let config = ProviderConfig {
+            api_key_env: Some("RESOLVE_TEST_UNSET_KEY".to_string()),
+            ..config
+        };
+        let error = config.resolve("resolve-test-eu").unwrap_err();
+        assert!(error.to_string().contains("RESOLVE_TEST_UNSET_KEY"));
+
+        // The conventional names imply the type
+        let resolved = ProviderConfig::default().resolve("ollama").unwrap();
+        assert_eq!(resolved.kind, Some(ProviderKind::Ollama));
     }
 }
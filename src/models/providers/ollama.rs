@@ -1,38 +1,281 @@
-use super::{ModelConfig, ModelProvider, ProviderConfig, ProviderSelector};
+use super::error::{self, ProviderError};
+use super::failover::classify_error;
+use super::host_pool::{HostLease, HostPool};
+use super::http::HttpClient;
+use super::stream::{self, FinishReason, StreamEvent, TokenStream, Usage};
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage as token_usage;
+use super::{ModelConfig, ModelProvider, OutputFormat, ProviderConfig, ProviderSelector};
 use async_trait::async_trait;
+use futures::stream as futures_stream;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::error::Error;
-use tracing::{debug, error, info};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+const DEFAULT_HOST: &str = "http://localhost:11434";
 
 pub struct OllamaProvider {
-    host: String,
+    client: HttpClient,
+    hosts: Arc<HostPool>,
+    chat: bool,
+    keep_alive: Option<String>,
+    options: HashMap<String, Value>,
+    /// Default model of the instance
+    model: Option<String>,
 }
 
+// Model parameters go under `options`; Ollama ignores them at the top level
 #[derive(Serialize)]
-struct GenerateRequest {
+struct Options<'a> {
+    temperature: f32,
+    num_predict: u32,
+    #[serde(flatten)]
+    extra: &'a HashMap<String, Value>,
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
     model: String,
-    prompt: String,
+    prompt: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
     stream: bool,
-    format: Option<String>,
-    temperature: f32,
-    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    options: Options<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: String,
+    messages: Vec<ChatMessage<'a>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    options: Options<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkMessage {
+    #[serde(default)]
+    content: String,
+}
+
+// One line of the NDJSON stream; the last one has `done` set, no text, the
+// reason generation stopped and the token counts (no prompt count when the
+// prompt was cached).
+// `/api/generate` streams `response`, `/api/chat` streams `message.content`.
 #[derive(Debug, Deserialize)]
-struct OllamaResponse {
+struct OllamaChunk {
+    #[serde(default)]
     response: String,
+    #[serde(default)]
+    message: Option<ChunkMessage>,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+// `format` is either "json" or a JSON schema the output must match
+fn format_for(output: &Option<OutputFormat>) -> Option<Value> {
+    match output {
+        Some(OutputFormat::Json) => Some(Value::String("json".to_string())),
+        Some(OutputFormat::Schema(schema)) => Some(schema.clone()),
+        None => None,
+    }
+}
+
+fn finish_reason(done_reason: &str) -> FinishReason {
+    match done_reason {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::Length,
+        _ => FinishReason::Other,
+    }
+}
+
+fn parse_chunk(line: &str) -> Result<Vec<StreamEvent>, Box<dyn Error + Send + Sync>> {
+    if line.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let chunk = serde_json::from_str::<OllamaChunk>(line)?;
+    if let Some(error) = chunk.error {
+        return Err(format!("Ollama error: {}", error).into());
+    }
+
+    let mut events = Vec::new();
+    let text = match chunk.message {
+        Some(message) => message.content,
+        None => chunk.response,
+    };
+    if !text.is_empty() {
+        events.push(StreamEvent::Text(text));
+    }
+    if chunk.prompt_eval_count.is_some() || chunk.eval_count.is_some() {
+        events.push(StreamEvent::Usage(Usage {
+            input_tokens: chunk.prompt_eval_count.unwrap_or(0),
+            output_tokens: chunk.eval_count.unwrap_or(0),
+        }));
+    }
+    if let Some(done_reason) = chunk.done_reason {
+        events.push(StreamEvent::Finished(finish_reason(&done_reason)));
+    }
+    Ok(events)
 }
 
 impl OllamaProvider {
-    pub fn new(config: &ProviderConfig) -> Self {
+    pub fn new(config: &ProviderConfig) -> Result<Self, Box<dyn Error + Send + Sync>> {
         // Check if enabled to keep compiler happy
         if !config.enabled {
             debug!("Creating Ollama provider, but it's disabled in config");
         }
 
-        Self {
-            host: config.host.clone().expect("Ollama host not specified"),
+        let urls = if config.hosts.is_empty() {
+            vec![config
+                .host
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HOST.to_string())]
+        } else {
+            config.hosts.clone()
+        };
+        let client = HttpClient::new(config.name_or("ollama"), &config.http)?;
+        let hosts = Arc::new(HostPool::new(urls, config.host_pool.clone()));
+        hosts.spawn_health_checks(client.clone());
+
+        Ok(Self {
+            client,
+            hosts,
+            chat: config.chat,
+            keep_alive: config.keep_alive.clone(),
+            options: config.options.clone(),
+            model: config.model.clone(),
+        })
+    }
+
+    /// Posts `body` to a host serving the model. Hosts that can't be reached
+    /// are reported to the pool and the next one is tried.
+    async fn send(
+        &self,
+        path: &str,
+        body: &Value,
+        model_name: &str,
+    ) -> Result<(HostLease, reqwest::Response), Box<dyn Error + Send + Sync>> {
+        let mut tried = Vec::new();
+        let mut unreachable: Option<reqwest::Error> = None;
+        loop {
+            let Some(lease) = self.hosts.acquire(model_name, &tried) else {
+                return Err(match unreachable {
+                    Some(e) => e.into(),
+                    None => format!("No Ollama host serves model {}", model_name).into(),
+                });
+            };
+            debug!(
+                "Sending request to Ollama {}/{} for model: {}",
+                lease.url(),
+                path,
+                model_name
+            );
+            let request = self
+                .client
+                .post(&format!("{}/{}", lease.url(), path))
+                .json(body);
+
+            match self.client.send(request).await {
+                Ok(response) => {
+                    if response.status().is_server_error() {
+                        lease.failed();
+                    } else {
+                        lease.succeeded();
+                    }
+                    return Ok((lease, response));
+                }
+                Err(e) => {
+                    if classify_error(&e).is_some() {
+                        lease.failed();
+                    }
+                    // Nothing was sent, so another host can take the request
+                    if !e.is_connect() {
+                        return Err(e.into());
+                    }
+                    warn!("Ollama host {} is unreachable: {}", lease.url(), e);
+                    tried.push(lease.index());
+                    unreachable = Some(e);
+                }
+            }
+        }
+    }
+
+    fn chat_body(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+        tools: Vec<Value>,
+        stream: bool,
+    ) -> Result<Value, serde_json::Error> {
+        let mut messages = Vec::new();
+        if let Some(system) = &config.system {
+            messages.push(ChatMessage {
+                role: "system",
+                content: system,
+            });
         }
+        messages.push(ChatMessage {
+            role: "user",
+            content: prompt,
+        });
+        serde_json::to_value(ChatRequest {
+            model: self.model_name(config),
+            messages,
+            stream,
+            format: format_for(&config.output),
+            options: Options {
+                temperature: config.temperature,
+                num_predict: config.max_tokens,
+                extra: &self.options,
+            },
+            keep_alive: self.keep_alive.as_deref(),
+            tools,
+        })
+    }
+
+    fn request_body(&self, prompt: &str, config: &ModelConfig) -> Result<Value, serde_json::Error> {
+        if self.chat {
+            return self.chat_body(prompt, config, vec![], true);
+        }
+
+        serde_json::to_value(GenerateRequest {
+            model: self.model_name(config),
+            prompt,
+            system: config.system.as_deref(),
+            stream: true,
+            format: format_for(&config.output),
+            options: Options {
+                temperature: config.temperature,
+                num_predict: config.max_tokens,
+                extra: &self.options,
+            },
+            keep_alive: self.keep_alive.as_deref(),
+        })
     }
 }
 
@@ -43,41 +286,483 @@ impl ModelProvider for OllamaProvider {
         prompt: &str,
         config: &ModelConfig,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let client = reqwest::Client::new();
+        let generation = stream::collect(self.generate_stream(prompt, config).await?).await?;
+        token_usage::record(
+            &self.model_name(config),
+            prompt,
+            config,
+            &generation.text,
+            generation.usage,
+        );
+        let response = generation.text;
 
+        if generation.finish_reason == Some(FinishReason::Length) {
+            warn!(
+                "Ollama response was cut at num_predict ({})",
+                config.max_tokens
+            );
+            return Err(ProviderError::Truncated {
+                model: self.model_name(config),
+                max_tokens: config.max_tokens,
+                partial: response,
+            }
+            .into());
+        }
+
+        if response.trim().is_empty() {
+            error!("Received empty response from Ollama");
+            return Err(ProviderError::EmptyResponse {
+                provider: "Ollama".to_string(),
+            }
+            .into());
+        }
+
+        info!("Successfully received response from Ollama");
+        Ok(response.trim().to_owned())
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
         // Get the appropriate Ollama model name
-        let model_name = ProviderSelector::get_model_name(config, false);
-
-        let request = GenerateRequest {
-            model: model_name.clone(),
-            prompt: prompt.to_string(),
-            stream: false,
-            format: None,
-            temperature: config.temperature,
-            max_tokens: config.max_tokens,
+        let model_name = self.model_name(config);
+        let path = if self.chat {
+            "api/chat"
+        } else {
+            "api/generate"
         };
 
-        debug!("Sending request to Ollama API for model: {}", model_name);
-        let response = client
-            .post(format!("{}/api/generate", self.host))
-            .json(&request)
-            .send()
+        let body = self.request_body(prompt, config)?;
+        let (lease, response) = self.send(path, &body, &model_name).await?;
+
+        if !response.status().is_success() {
+            let error = ProviderError::Http {
+                provider: "Ollama".to_string(),
+                status: response.status().as_u16(),
+                retry_after: error::retry_after(response.headers()),
+                body: response.text().await.unwrap_or_default(),
+            };
+            error!("{}", error);
+            return Err(error.into());
+        }
+
+        // Ollama streams one JSON object per line. The host stays leased
+        // until the stream is dropped.
+        let tokens = stream::lines(response).flat_map(move |line| {
+            let _lease = &lease;
+            let events = match line.and_then(|line| parse_chunk(&line)) {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures_stream::iter(events)
+        });
+        Ok(Box::pin(tokens))
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let tools = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    }
+                })
+            })
+            .collect();
+        let body = self.chat_body(prompt, config, tools, false)?;
+
+        let (_lease, response) = self
+            .send("api/chat", &body, &self.model_name(config))
             .await?;
 
         if !response.status().is_success() {
-            let error_msg = format!("Ollama request failed: {}", response.status());
-            error!("{}", error_msg);
-            return Err(error_msg.into());
+            let status = response.status().as_u16();
+            let retry_after = error::retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            // Models without tool support are rejected with a 400
+            if status == 400 && body.contains("does not support tools") {
+                return Err(ProviderError::ToolsUnsupported {
+                    model: self.model_name(config),
+                }
+                .into());
+            }
+            return Err(ProviderError::Http {
+                provider: "Ollama".to_string(),
+                status,
+                body,
+                retry_after,
+            }
+            .into());
         }
 
-        let response_obj = response.json::<OllamaResponse>().await?;
+        let response: Value = response.json().await?;
+        token_usage::record(
+            &self.model_name(config),
+            prompt,
+            config,
+            &response["message"].to_string(),
+            Some(Usage {
+                input_tokens: response["prompt_eval_count"].as_u64().unwrap_or(0) as u32,
+                output_tokens: response["eval_count"].as_u64().unwrap_or(0) as u32,
+            }),
+        );
+        let call = response["message"]["tool_calls"]
+            .as_array()
+            .and_then(|calls| calls.first())
+            .map(|call| ToolCall {
+                name: call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: call["function"]["arguments"].clone(),
+            });
+        Ok(call)
+    }
 
-        if response_obj.response.trim().is_empty() {
-            error!("Received empty response from Ollama");
-            return Err("Empty response from Ollama".into());
+    /// Lists the models of the hosts with `/api/tags`, without loading any
+    async fn check_model(&self, config: &ModelConfig) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.hosts.check(&self.client).await;
+        let model_name = self.model_name(config);
+        match self.hosts.availability(&model_name) {
+            (0, _) => Err("no Ollama host is reachable".into()),
+            (_, 0) => Err(format!(
+                "model {} is not pulled on any host (ollama pull {})",
+                model_name, model_name
+            )
+            .into()),
+            _ => Ok(()),
         }
+    }
 
-        info!("Successfully received response from Ollama");
-        Ok(response_obj.response.trim().to_owned())
+    fn model_name(&self, config: &ModelConfig) -> String {
+        match &self.model {
+            Some(model) if config.ollama.is_empty() => model.clone(),
+            _ => ProviderSelector::get_model_name(config, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::host_pool::HostPoolConfig;
+    use crate::models::providers::test_server::{streaming_server, stub_server};
+
+    fn model_config() -> ModelConfig {
+        ModelConfig {
+            ollama: "llama2".to_string(),
+            temperature: 0.1,
+            max_tokens: 100,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_ndjson_tokens() {
+        // Lines are split across network chunks
+        let (address, request) = streaming_server(
+            "application/x-ndjson",
+            &[
+                "{\"response\":\"{\\\"to\\\": \",\"done\":false}\n{\"resp",
+                "onse\":\"\\\"Alice\\\"}\",\"done\":false}\n",
+                "{\"response\":\"\",\"done\":true}\n",
+            ],
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let tokens: Vec<StreamEvent> = provider
+            .generate_stream("hello", &model_config())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let tokens: Vec<&str> = tokens
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tokens, vec!["{\"to\": ", "\"Alice\"}"]);
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /api/generate"));
+        assert!(request.contains(r#""stream":true"#));
+    }
+
+    #[test]
+    fn test_final_chunk_reports_finish_reason_and_usage() {
+        let events = parse_chunk(
+            r#"{"response":"","done":true,"done_reason":"stop","prompt_eval_count":26,"eval_count":12}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Usage(Usage {
+                    input_tokens: 26,
+                    output_tokens: 12,
+                }),
+                StreamEvent::Finished(FinishReason::Stop),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_request_options_and_format() {
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some("http://localhost:11434".to_string()),
+            keep_alive: Some("10m".to_string()),
+            options: HashMap::from([("num_ctx".to_string(), serde_json::json!(8192))]),
+            ..Default::default()
+        })
+        .unwrap();
+        let model = model_config().with_output(OutputFormat::Json);
+
+        let body = provider.request_body("hello", &model).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "model": "llama2",
+                "prompt": "hello",
+                "stream": true,
+                "format": "json",
+                "options": {"temperature": 0.1f32, "num_predict": 100, "num_ctx": 8192},
+                "keep_alive": "10m",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_mode_with_schema() {
+        let (address, request) = streaming_server(
+            "application/x-ndjson",
+            &[
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"{\\\"to\\\": \\\"Alice\\\"}\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n",
+            ],
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            chat: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let schema = serde_json::json!({"type": "object", "required": ["to"]});
+        let model = ModelConfig {
+            system: Some("Answer in JSON".to_string()),
+            ..model_config()
+        }
+        .with_output(OutputFormat::Schema(schema.clone()));
+
+        let response = provider.generate("hello", &model).await.unwrap();
+        assert_eq!(response, r#"{"to": "Alice"}"#);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /api/chat"));
+        let body: Value =
+            serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "hello");
+        assert_eq!(body["format"], schema);
+    }
+
+    #[tokio::test]
+    async fn test_generate_collects_stream_and_reports_errors() {
+        let (address, _request) = streaming_server(
+            "application/x-ndjson",
+            &["{\"response\":\" ok \",\"done\":false}\n{\"response\":\"\",\"done\":true}"],
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            provider.generate("hello", &model_config()).await.unwrap(),
+            "ok"
+        );
+
+        let (address, _request) = streaming_server(
+            "application/x-ndjson",
+            &["{\"error\":\"model 'llama2' not found\"}\n"],
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        let error = provider
+            .generate("hello", &model_config())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_reports_truncation() {
+        let (address, _request) = streaming_server(
+            "application/x-ndjson",
+            &["{\"response\":\"{\\\"to\\\": \",\"done\":false}\n{\"response\":\"\",\"done\":true,\"done_reason\":\"length\"}\n"],
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let error = provider
+            .generate("hello", &model_config())
+            .await
+            .unwrap_err();
+        match error.downcast_ref::<ProviderError>() {
+            Some(ProviderError::Truncated {
+                model,
+                max_tokens,
+                partial,
+            }) => {
+                assert_eq!((model.as_str(), *max_tokens), ("llama2", 100));
+                assert_eq!(partial, "{\"to\": ");
+            }
+            _ => panic!("expected a truncation error, got {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_and_unsupported_models() {
+        let (address, request) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"send_email","arguments":{"to":"alice@example.com"}}}]},"done":true}"#,
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        let tools = vec![ToolDefinition {
+            name: "send_email".to_string(),
+            description: "Send an email".to_string(),
+            parameters: json!({"type": "object"}),
+        }];
+
+        let call = provider
+            .call_tool("email alice@example.com", &model_config(), &tools)
+            .await
+            .unwrap();
+        assert_eq!(
+            call,
+            Some(ToolCall {
+                name: "send_email".to_string(),
+                arguments: json!({"to": "alice@example.com"}),
+            })
+        );
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /api/chat"));
+        assert!(request.contains(r#""type":"function""#));
+
+        let (address, _request) = stub_server(
+            "400 Bad Request",
+            "application/json",
+            r#"{"error":"registry.ollama.ai/library/llama2:latest does not support tools"}"#,
+        )
+        .await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        let error = provider
+            .call_tool("hello", &model_config(), &tools)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ProviderError>(),
+            Some(ProviderError::ToolsUnsupported { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_host_is_skipped() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unreachable = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let (address, request) = stub_server(
+            "200 OK",
+            "application/x-ndjson",
+            "{\"response\":\"{}\",\"done\":true,\"done_reason\":\"stop\"}\n",
+        )
+        .await;
+
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            hosts: vec![unreachable, address],
+            host_pool: HostPoolConfig {
+                health_check_interval_secs: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let response = provider.generate("hello", &model_config()).await.unwrap();
+        assert_eq!(response, "{}");
+        assert!(request.await.unwrap().starts_with("POST /api/generate"));
+    }
+
+    #[tokio::test]
+    async fn test_check_model_lists_tags() {
+        let tags = r#"{"models":[{"name":"llama2:latest"}]}"#;
+        let (address, request) = stub_server("200 OK", "application/json", tags).await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+        provider.check_model(&model_config()).await.unwrap();
+        assert!(request.await.unwrap().starts_with("GET /api/tags"));
+
+        let (address, _request) = stub_server("200 OK", "application/json", tags).await;
+        let provider = OllamaProvider::new(&ProviderConfig {
+            enabled: true,
+            host: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        let missing = ModelConfig {
+            ollama: "deepseek-r1:8b".to_string(),
+            ..model_config()
+        };
+        let error = provider.check_model(&missing).await.unwrap_err();
+        assert!(error.to_string().contains("ollama pull deepseek-r1:8b"));
     }
 }
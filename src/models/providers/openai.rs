@@ -0,0 +1,400 @@
+// src/models/providers/openai.rs - Provider for OpenAI-compatible servers (vLLM, llama.cpp, ...)
+
+use super::error::{self, ProviderError};
+use super::http::HttpClient;
+use super::stream::Usage;
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage as token_usage;
+use super::{ModelConfig, ModelProvider, OutputFormat, ProviderConfig, ProviderSelector};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use tracing::{debug, error, info, warn};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+pub struct OpenAiProvider {
+    client: HttpClient,
+    base_url: String,
+    api_key: Option<String>,
+    headers: HashMap<String, String>,
+    json_mode: bool,
+    /// Default model of the instance
+    model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    temperature: f32,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<ResponseUsage> for Usage {
+    fn from(usage: ResponseUsage) -> Self {
+        Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChoiceToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceToolCall {
+    function: ChoiceFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceFunction {
+    name: String,
+    /// JSON-encoded arguments
+    arguments: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: &ProviderConfig) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        // Check if enabled to keep compiler happy
+        if !config.enabled {
+            debug!("Creating OpenAI-compatible provider, but it's disabled in config");
+        }
+
+        let base_url = config
+            .base_url
+            .clone()
+            .or_else(|| config.host.clone())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        Ok(Self {
+            client: HttpClient::new(config.name_or("openai"), &config.http)?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|key| !key.is_empty()),
+            headers: config.headers.clone(),
+            json_mode: config.json_mode,
+            model: config.model.clone(),
+        })
+    }
+
+    async fn send(
+        &self,
+        request: &ChatRequest,
+    ) -> Result<ChatResponse, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Sending chat completion request to {} for model: {}",
+            self.base_url, request.model
+        );
+        let mut builder = self
+            .client
+            .post(&format!("{}/chat/completions", self.base_url))
+            .json(request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+
+        let response = self.client.send(builder).await?;
+
+        if !response.status().is_success() {
+            let error = ProviderError::Http {
+                provider: "Chat completion".to_string(),
+                status: response.status().as_u16(),
+                retry_after: error::retry_after(response.headers()),
+                body: response.text().await.unwrap_or_default(),
+            };
+            error!("{}", error);
+            return Err(error.into());
+        }
+
+        Ok(response.json::<ChatResponse>().await?)
+    }
+}
+
+fn messages(prompt: &str, config: &ModelConfig) -> Vec<Message> {
+    let mut messages = Vec::new();
+    if let Some(system) = &config.system {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: system.clone(),
+        });
+    }
+    messages.push(Message {
+        role: "user".to_string(),
+        content: prompt.to_string(),
+    });
+    messages
+}
+
+#[async_trait]
+impl ModelProvider for OpenAiProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let model_name = self.model_name(config);
+
+        let messages = messages(prompt, config);
+
+        // Only servers known to support `response_format` are asked for constrained output
+        let response_format = match (&config.output, self.json_mode) {
+            (Some(OutputFormat::Json), true) => Some(json!({"type": "json_object"})),
+            (Some(OutputFormat::Schema(schema)), true) => Some(json!({
+                "type": "json_schema",
+                "json_schema": {"name": "output", "schema": schema},
+            })),
+            _ => None,
+        };
+
+        let request = ChatRequest {
+            model: model_name.clone(),
+            messages,
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            response_format,
+            tools: Vec::new(),
+            tool_choice: None,
+        };
+
+        let response_obj = self.send(&request).await?;
+        let usage = response_obj.usage.map(Usage::from);
+        let choice = response_obj.choices.into_iter().next();
+        let truncated = choice
+            .as_ref()
+            .is_some_and(|choice| choice.finish_reason.as_deref() == Some("length"));
+        let content = choice
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        token_usage::record(&model_name, prompt, config, &content, usage);
+
+        if truncated {
+            warn!(
+                "Chat completion was cut at max_tokens ({})",
+                config.max_tokens
+            );
+            return Err(ProviderError::Truncated {
+                model: model_name,
+                max_tokens: config.max_tokens,
+                partial: content,
+            }
+            .into());
+        }
+
+        if content.trim().is_empty() {
+            error!("Received empty response from chat completion server");
+            return Err(ProviderError::EmptyResponse {
+                provider: "chat completion server".to_string(),
+            }
+            .into());
+        }
+
+        info!("Successfully received response from chat completion server");
+        Ok(content.trim().to_owned())
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        config: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        let request = ChatRequest {
+            model: self.model_name(config),
+            messages: messages(prompt, config),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            response_format: None,
+            tools: tools
+                .iter()
+                .map(|tool| json!({"type": "function", "function": tool}))
+                .collect(),
+            tool_choice: Some(json!("required")),
+        };
+
+        let response = self.send(&request).await?;
+        let usage = response.usage.map(Usage::from);
+        let call = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.tool_calls.into_iter().next());
+        let arguments_text = call
+            .as_ref()
+            .map(|call| call.function.arguments.as_str())
+            .unwrap_or_default();
+        token_usage::record(&request.model, prompt, config, arguments_text, usage);
+
+        let Some(call) = call else {
+            return Ok(None);
+        };
+        let arguments = if call.function.arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&call.function.arguments)?
+        };
+        Ok(Some(ToolCall {
+            name: call.function.name,
+            arguments,
+        }))
+    }
+
+    fn model_name(&self, config: &ModelConfig) -> String {
+        match &self.model {
+            Some(model) if config.openai.is_empty() => model.clone(),
+            _ => ProviderSelector::get_openai_model_name(config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::test_server::stub_server;
+
+    fn model_config() -> ModelConfig {
+        ModelConfig {
+            openai: "qwen2.5-7b-instruct".to_string(),
+            temperature: 0.1,
+            max_tokens: 200,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_against_stub_server() {
+        let (address, request) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"choices":[{"index":0,"message":{"role":"assistant","content":" {\"ok\": true} "}}]}"#,
+        )
+        .await;
+
+        let provider = OpenAiProvider::new(&ProviderConfig {
+            enabled: true,
+            base_url: Some(format!("{}/v1/", address)),
+            api_key: Some("secret".to_string()),
+            headers: HashMap::from([("x-tenant".to_string(), "acme".to_string())]),
+            json_mode: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let model = model_config().with_output(OutputFormat::Json);
+        let response = provider.generate("hello", &model).await.unwrap();
+        assert_eq!(response, r#"{"ok": true}"#);
+
+        let request = request.await.unwrap().to_lowercase();
+        assert!(request.starts_with("post /v1/chat/completions"));
+        assert!(request.contains("authorization: bearer secret"));
+        assert!(request.contains("x-tenant: acme"));
+        assert!(request.contains(r#""model":"qwen2.5-7b-instruct""#));
+        assert!(request.contains(r#""response_format":{"type":"json_object"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_generate_reports_server_errors() {
+        let (address, _request) = stub_server(
+            "503 Service Unavailable",
+            "application/json",
+            r#"{"error":"loading"}"#,
+        )
+        .await;
+
+        let provider = OpenAiProvider::new(&ProviderConfig {
+            enabled: true,
+            base_url: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let error = provider
+            .generate("hello", &model_config())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("503"));
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_parses_encoded_arguments() {
+        let (address, request) = stub_server(
+            "200 OK",
+            "application/json",
+            r#"{"choices":[{"index":0,"message":{"role":"assistant","content":null,"tool_calls":[{"id":"c1","type":"function","function":{"name":"send_email","arguments":"{\"to\": \"alice@example.com\"}"}}]}}]}"#,
+        )
+        .await;
+
+        let provider = OpenAiProvider::new(&ProviderConfig {
+            enabled: true,
+            base_url: Some(address),
+            ..Default::default()
+        })
+        .unwrap();
+        let tools = vec![ToolDefinition {
+            name: "send_email".to_string(),
+            description: "Send an email".to_string(),
+            parameters: json!({"type": "object", "properties": {"to": {"type": "string"}}}),
+        }];
+
+        let call = provider
+            .call_tool("email alice@example.com", &model_config(), &tools)
+            .await
+            .unwrap();
+        assert_eq!(
+            call,
+            Some(ToolCall {
+                name: "send_email".to_string(),
+                arguments: json!({"to": "alice@example.com"}),
+            })
+        );
+
+        let request = request.await.unwrap();
+        assert!(request.contains(r#""tool_choice":"required""#));
+        assert!(request.contains(r#""tools":[{"function":{"description":"Send an email""#));
+    }
+}
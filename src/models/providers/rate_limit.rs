@@ -0,0 +1,518 @@
+// src/models/providers/rate_limit.rs - Request rate, token rate and concurrency limits of a provider
+
+use super::error::ProviderError;
+use super::stream::TokenStream;
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage::estimate_tokens;
+use super::{ModelConfig, ModelProvider};
+use crate::metrics;
+use crate::workflow::trace::{self, TraceEvent};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep, sleep_until, Instant};
+use tracing::{debug, warn};
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_max_retry_after_secs() -> u64 {
+    60
+}
+
+/// Limits of a provider, shared by every workflow calling it. Unset limits don't apply.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub requests_per_second: Option<f64>,
+    /// Tokens per minute, counting the prompt and `max_tokens` of each call
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    /// Calls rejected with 429 are sent again this many times
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Longer `Retry-After` delays fail the call instead of waiting
+    #[serde(default = "default_max_retry_after_secs")]
+    pub max_retry_after_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            tokens_per_minute: None,
+            max_concurrent: None,
+            max_retries: default_max_retries(),
+            max_retry_after_secs: default_max_retry_after_secs(),
+        }
+    }
+}
+
+struct Pacing {
+    /// Earliest start of the next request
+    next_slot: Instant,
+    /// Token bucket of the tokens-per-minute limit
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Admits calls in arrival order: the pacing lock and the semaphore both
+/// serve their waiters first come, first served
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    pacing: tokio::sync::Mutex<Pacing>,
+    /// Set by a `Retry-After`; no call starts before it
+    paused_until: Mutex<Option<Instant>>,
+    concurrency: Option<Arc<Semaphore>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            pacing: tokio::sync::Mutex::new(Pacing {
+                next_slot: now,
+                tokens: config.tokens_per_minute.unwrap_or(0) as f64,
+                refilled_at: now,
+            }),
+            paused_until: Mutex::new(None),
+            concurrency: config
+                .max_concurrent
+                .filter(|max| *max > 0)
+                .map(|max| Arc::new(Semaphore::new(max))),
+            config,
+        }
+    }
+
+    /// Waits until a call of `tokens` tokens may start. The returned permit
+    /// holds a concurrency slot until it is dropped.
+    pub async fn acquire(&self, tokens: u32) -> Option<OwnedSemaphorePermit> {
+        {
+            let mut pacing = self.pacing.lock().await;
+
+            loop {
+                let paused_until = *self.paused_until.lock().unwrap();
+                match paused_until {
+                    Some(until) if until > Instant::now() => sleep_until(until).await,
+                    _ => break,
+                }
+            }
+
+            if let Some(rps) = self.config.requests_per_second.filter(|rps| *rps > 0.0) {
+                sleep_until(pacing.next_slot).await;
+                pacing.next_slot = Instant::now() + Duration::from_secs_f64(1.0 / rps);
+            }
+
+            if let Some(tpm) = self.config.tokens_per_minute.filter(|tpm| *tpm > 0) {
+                let capacity = tpm as f64;
+                let per_second = capacity / 60.0;
+                // A call larger than the whole budget waits for a full bucket
+                let needed = (tokens as f64).min(capacity);
+                let refill = |pacing: &mut Pacing| {
+                    let now = Instant::now();
+                    let elapsed = now.duration_since(pacing.refilled_at).as_secs_f64();
+                    pacing.tokens = (pacing.tokens + elapsed * per_second).min(capacity);
+                    pacing.refilled_at = now;
+                };
+                refill(&mut pacing);
+                if pacing.tokens < needed {
+                    sleep(Duration::from_secs_f64(
+                        (needed - pacing.tokens) / per_second,
+                    ))
+                    .await;
+                    refill(&mut pacing);
+                }
+                pacing.tokens -= needed;
+            }
+        }
+
+        match &self.concurrency {
+            Some(semaphore) => Some(
+                semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("rate limiter semaphore is never closed"),
+            ),
+            None => None,
+        }
+    }
+
+    /// Holds back every call until `delay` has passed
+    pub fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+}
+
+/// Passes the calls of a provider through its [`RateLimiter`], and sends
+/// calls rejected with 429 again once the delay the server asked for is over
+pub struct RateLimitedProvider {
+    name: String,
+    inner: Arc<dyn ModelProvider>,
+    limiter: Arc<RateLimiter>,
+    /// Off for failover chain members, whose 429s go straight to the chain
+    retry_rate_limited: bool,
+}
+
+impl RateLimitedProvider {
+    pub fn new(name: &str, inner: Arc<dyn ModelProvider>, config: RateLimitConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            inner,
+            limiter: Arc::new(RateLimiter::new(config)),
+            retry_rate_limited: true,
+        }
+    }
+
+    async fn limited<T, F, Fut>(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        call: F,
+    ) -> Result<T, Box<dyn Error + Send + Sync>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+    {
+        let (value, _permit) = self.limited_holding(prompt, model, call).await?;
+        Ok(value)
+    }
+
+    // Like `limited`, returning the concurrency permit for the caller to hold
+    // while the response is still being read
+    async fn limited_holding<T, F, Fut>(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        call: F,
+    ) -> Result<(T, Option<OwnedSemaphorePermit>), Box<dyn Error + Send + Sync>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+    {
+        let tokens = estimate_tokens(prompt)
+            + model.system.as_deref().map_or(0, estimate_tokens)
+            + model.max_tokens;
+        let labels = [("provider", self.name.as_str())];
+        let mut retries = 0;
+
+        loop {
+            let queued_at = Instant::now();
+            let permit = self.limiter.acquire(tokens).await;
+            let waited = queued_at.elapsed();
+            if waited >= Duration::from_millis(1) {
+                debug!("Waited {} ms for {}", waited.as_millis(), self.name);
+                metrics::increment(
+                    &metrics::labeled("rate_limit_wait_ms", &labels),
+                    waited.as_millis() as u64,
+                );
+            }
+
+            let error = match call().await {
+                Ok(value) => return Ok((value, permit)),
+                Err(error) => error,
+            };
+            drop(permit);
+            let Some(provider_error) = error
+                .downcast_ref::<ProviderError>()
+                .filter(|e| e.is_rate_limited())
+            else {
+                return Err(error);
+            };
+            if !self.retry_rate_limited {
+                debug!(
+                    "{} is rate limited, leaving it to the failover chain",
+                    self.name
+                );
+                return Err(error);
+            }
+
+            // Without a Retry-After, back off exponentially from one second
+            let delay = provider_error
+                .retry_after()
+                .unwrap_or(Duration::from_secs(1 << retries.min(5)));
+            if retries >= self.limiter.config.max_retries
+                || delay > Duration::from_secs(self.limiter.config.max_retry_after_secs)
+            {
+                return Err(error);
+            }
+            retries += 1;
+
+            warn!(
+                "{} is rate limited, retrying in {} ms",
+                self.name,
+                delay.as_millis()
+            );
+            metrics::increment(&metrics::labeled("rate_limit_retries", &labels), 1);
+            trace::record(TraceEvent::RateLimited {
+                provider: self.name.clone(),
+                retry_after_ms: delay.as_millis() as u64,
+            });
+            // Every caller of the provider waits, not just this one
+            self.limiter.pause(delay);
+        }
+    }
+}
+
+#[async_trait]
+impl ModelProvider for RateLimitedProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.limited(prompt, model, || self.inner.generate(prompt, model))
+            .await
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        let (tokens, permit) = self
+            .limited_holding(prompt, model, || self.inner.generate_stream(prompt, model))
+            .await?;
+        // The call counts against `max_concurrent` until its stream is dropped
+        Ok(Box::pin(tokens.map(move |event| {
+            let _ = &permit;
+            event
+        })))
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        self.limited(prompt, model, || self.inner.call_tool(prompt, model, tools))
+            .await
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.inner.model_name(model)
+    }
+
+    // Same limits, shared with the direct callers, without the 429 retries
+    fn failover_member(&self) -> Option<Arc<dyn ModelProvider>> {
+        Some(Arc::new(Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+            limiter: self.limiter.clone(),
+            retry_rate_limited: false,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::ProviderRegistry;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Takes 30 ms per call, tracks the calls in flight and rejects the first
+    // `rejections` calls with a 429
+    #[derive(Default)]
+    struct BusyProvider {
+        rejections: usize,
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ModelProvider for BusyProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.rejections {
+                return Err(ProviderError::Http {
+                    provider: "Busy".to_string(),
+                    status: 429,
+                    body: String::new(),
+                    retry_after: Some(Duration::from_millis(100)),
+                }
+                .into());
+            }
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            sleep(Duration::from_millis(30)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok("ok".to_string())
+        }
+    }
+
+    async fn run_concurrently(provider: Arc<RateLimitedProvider>, calls: usize) {
+        let calls = (0..calls).map(|_| {
+            let provider = provider.clone();
+            tokio::spawn(async move {
+                provider
+                    .generate("hello", &ModelConfig::default())
+                    .await
+                    .unwrap()
+            })
+        });
+        for call in futures::future::join_all(calls).await {
+            assert_eq!(call.unwrap(), "ok");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_cap() {
+        let inner = Arc::new(BusyProvider::default());
+        let config = RateLimitConfig {
+            max_concurrent: Some(2),
+            ..Default::default()
+        };
+        let provider = Arc::new(RateLimitedProvider::new("busy", inner.clone(), config));
+
+        run_concurrently(provider, 6).await;
+        assert_eq!(inner.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_streams_hold_their_slot_until_dropped() {
+        let config = RateLimitConfig {
+            max_concurrent: Some(1),
+            ..Default::default()
+        };
+        let provider = RateLimitedProvider::new("busy", Arc::new(BusyProvider::default()), config);
+        let model = ModelConfig::default();
+
+        let tokens = provider.generate_stream("hello", &model).await.unwrap();
+        let queued = tokio::time::timeout(
+            Duration::from_millis(100),
+            provider.generate("hello", &model),
+        );
+        assert!(queued.await.is_err());
+
+        drop(tokens);
+        assert_eq!(provider.generate("hello", &model).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_requests_per_second_and_tokens_per_minute() {
+        let config = RateLimitConfig {
+            requests_per_second: Some(20.0),
+            ..Default::default()
+        };
+        let provider = Arc::new(RateLimitedProvider::new(
+            "busy",
+            Arc::new(BusyProvider::default()),
+            config,
+        ));
+        let started = Instant::now();
+        run_concurrently(provider, 3).await;
+        // The third call starts two intervals of 50 ms after the first
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        // 600 tokens per minute refill 10 per second; each call needs 2
+        let config = RateLimitConfig {
+            tokens_per_minute: Some(600),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(config);
+        let started = Instant::now();
+        limiter.acquire(599).await;
+        limiter.acquire(2).await;
+        let waited = started.elapsed();
+        assert!(waited >= Duration::from_millis(100), "{:?}", waited);
+    }
+
+    #[tokio::test]
+    async fn test_failover_members_leave_429s_to_the_chain() {
+        let inner = Arc::new(BusyProvider {
+            rejections: 2,
+            ..Default::default()
+        });
+        let mut registry = ProviderRegistry::new("chain");
+        registry.register(
+            "busy",
+            Arc::new(RateLimitedProvider::new(
+                "busy",
+                inner.clone(),
+                RateLimitConfig::default(),
+            )),
+        );
+        registry.register("spare", Arc::new(BusyProvider::default()));
+        registry
+            .register_failover("chain", &["busy".to_string(), "spare".to_string()])
+            .unwrap();
+
+        let started = Instant::now();
+        let response = registry
+            .resolve(None)
+            .unwrap()
+            .generate("hello", &ModelConfig::default())
+            .await;
+        assert_eq!(response.unwrap(), "ok");
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        // Called directly, the provider still waits out the 429
+        let response = registry
+            .resolve(Some("busy"))
+            .unwrap()
+            .generate("hello", &ModelConfig::default())
+            .await;
+        assert_eq!(response.unwrap(), "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_pauses_and_retries() {
+        let inner = Arc::new(BusyProvider {
+            rejections: 1,
+            ..Default::default()
+        });
+        let provider = RateLimitedProvider::new("busy", inner.clone(), RateLimitConfig::default());
+
+        let trace = trace::ExecutionTrace::default();
+        let started = Instant::now();
+        let response = trace
+            .scope(provider.generate("hello", &ModelConfig::default()))
+            .await;
+        assert_eq!(response.unwrap(), "ok");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            trace.entries()[0].event,
+            TraceEvent::RateLimited {
+                provider: "busy".to_string(),
+                retry_after_ms: 100,
+            }
+        );
+
+        // Out of retries, the 429 is returned
+        let inner = Arc::new(BusyProvider {
+            rejections: 5,
+            ..Default::default()
+        });
+        let config = RateLimitConfig {
+            max_retries: 1,
+            ..Default::default()
+        };
+        let provider = RateLimitedProvider::new("busy", inner.clone(), config);
+        let error = provider
+            .generate("hello", &ModelConfig::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("429"));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
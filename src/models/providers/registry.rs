@@ -0,0 +1,156 @@
+use super::failover::FailoverProvider;
+use super::hedged::LatencyWindow;
+use super::ModelProvider;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Named set of providers that workflow steps can route to.
+/// One of them is the default, used by steps that don't name a provider.
+pub struct ProviderRegistry {
+    providers: HashMap<String, Arc<dyn ModelProvider>>,
+    default_name: String,
+    /// Observed latencies by provider and model, kept across requests for hedging
+    latencies: Mutex<HashMap<String, Arc<LatencyWindow>>>,
+}
+
+impl ProviderRegistry {
+    /// Registry whose default is the provider registered as `default_name`,
+    /// which may be a failover chain registered last
+    pub fn new(default_name: &str) -> Self {
+        Self {
+            providers: HashMap::new(),
+            default_name: default_name.to_string(),
+            latencies: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, provider: Arc<dyn ModelProvider>) {
+        self.providers.insert(name.to_string(), provider);
+    }
+
+    /// Registers a failover provider trying the named providers in order
+    pub fn register_failover(
+        &mut self,
+        name: &str,
+        chain: &[String],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if chain.is_empty() {
+            return Err(format!("Failover provider '{}' has an empty chain", name).into());
+        }
+        let members = chain
+            .iter()
+            .map(|member| {
+                let provider = self.resolve(Some(member))?;
+                let provider = provider.failover_member().unwrap_or(provider);
+                Ok((member.clone(), provider))
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+        self.register(name, Arc::new(FailoverProvider::new(members)));
+        Ok(())
+    }
+
+    /// Wraps every registered provider, e.g. to record or replay its calls
+    pub fn wrap<F>(mut self, wrapper: F) -> Self
+    where
+        F: Fn(&str, Arc<dyn ModelProvider>) -> Arc<dyn ModelProvider>,
+    {
+        for (name, provider) in self.providers.iter_mut() {
+            *provider = wrapper(name, provider.clone());
+        }
+        self
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.providers.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Name of the provider `resolve` returns for `name`
+    pub fn resolved_name<'a>(&'a self, name: Option<&'a str>) -> &'a str {
+        name.unwrap_or(&self.default_name)
+    }
+
+    /// Latency window of a provider and model, created on first use
+    pub fn latency_window(&self, provider: &str, model: &str) -> Arc<LatencyWindow> {
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(format!("{}/{}", provider, model))
+            .or_default()
+            .clone()
+    }
+
+    /// Resolves a provider by name, falling back to the default when no name is given
+    pub fn resolve(
+        &self,
+        name: Option<&str>,
+    ) -> Result<Arc<dyn ModelProvider>, Box<dyn Error + Send + Sync>> {
+        let name = self.resolved_name(name);
+        self.providers.get(name).cloned().ok_or_else(|| {
+            format!(
+                "Provider '{}' is not configured (available: {})",
+                name,
+                self.names().join(", ")
+            )
+            .into()
+        })
+    }
+}
+
+impl std::fmt::Debug for ProviderRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderRegistry")
+            .field("providers", &self.names())
+            .field("default_name", &self.default_name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::ModelConfig;
+    use async_trait::async_trait;
+
+    struct EchoProvider(&'static str);
+
+    #[async_trait]
+    impl ModelProvider for EchoProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_by_name_and_default() {
+        let mut registry = ProviderRegistry::new("claude");
+        assert!(registry.resolve(None).is_err());
+        registry.register("claude", Arc::new(EchoProvider("claude")));
+        registry.register("ollama", Arc::new(EchoProvider("ollama")));
+
+        let config = ModelConfig::default();
+        let default = registry.resolve(None).unwrap();
+        assert_eq!(default.generate("", &config).await.unwrap(), "claude");
+
+        let ollama = registry.resolve(Some("ollama")).unwrap();
+        assert_eq!(ollama.generate("", &config).await.unwrap(), "ollama");
+
+        assert!(registry.resolve(Some("openai")).is_err());
+        assert_eq!(registry.names(), vec!["claude", "ollama"]);
+
+        let chain = vec!["claude".to_string(), "ollama".to_string()];
+        registry.register_failover("resilient", &chain).unwrap();
+        let resilient = registry.resolve(Some("resilient")).unwrap();
+        assert_eq!(resilient.generate("", &config).await.unwrap(), "claude");
+
+        let broken = vec!["claude".to_string(), "openai".to_string()];
+        assert!(registry.register_failover("broken", &broken).is_err());
+        assert!(registry.register_failover("empty", &[]).is_err());
+    }
+}
@@ -17,4 +17,13 @@ impl ProviderSelector {
             }
         }
     }
+
+    // OpenAI-compatible servers name models freely, so fall back to the generic name
+    pub fn get_openai_model_name(config: &super::ModelConfig) -> String {
+        if !config.openai.is_empty() {
+            config.openai.clone()
+        } else {
+            config.name.clone()
+        }
+    }
 }
@@ -0,0 +1,123 @@
+// src/models/providers/stream.rs - Token streams of streaming model APIs
+
+use crate::workflow::progress;
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use std::error::Error;
+use std::pin::Pin;
+
+/// Why the model stopped generating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// Natural end of the answer or a stop sequence
+    Stop,
+    /// The `max_tokens` budget ran out, the answer is cut
+    Length,
+    /// The model called a tool
+    ToolUse,
+    /// The model declined to answer
+    Refusal,
+    Other,
+}
+
+/// Tokens consumed by a call, as reported by the provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// Item of a [`TokenStream`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Text(String),
+    /// Token counts so far; later events supersede earlier ones field by field
+    Usage(Usage),
+    Finished(FinishReason),
+}
+
+/// Events of a response, in the order the model produced them. Dropping the
+/// stream closes the connection, which cancels the generation.
+pub type TokenStream =
+    Pin<Box<dyn Stream<Item = Result<StreamEvent, Box<dyn Error + Send + Sync>>> + Send>>;
+
+/// A complete response with what the provider reported about it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Generation {
+    pub text: String,
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+}
+
+struct LineReader {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+/// Splits a response body into lines as it arrives, for NDJSON and
+/// server-sent events. A trailing `\r` is removed.
+pub fn lines(
+    response: reqwest::Response,
+) -> impl Stream<Item = Result<String, Box<dyn Error + Send + Sync>>> + Send {
+    let reader = LineReader {
+        response,
+        buffer: Vec::new(),
+        done: false,
+    };
+
+    stream::unfold(reader, |mut reader| async move {
+        loop {
+            if let Some(end) = reader.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = reader.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line[..end]);
+                return Some((Ok(line.trim_end_matches('\r').to_string()), reader));
+            }
+            if reader.done {
+                if reader.buffer.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&reader.buffer).to_string();
+                reader.buffer.clear();
+                return Some((Ok(line), reader));
+            }
+            match reader.response.chunk().await {
+                Ok(Some(chunk)) => reader.buffer.extend_from_slice(&chunk),
+                Ok(None) => reader.done = true,
+                Err(e) => {
+                    reader.done = true;
+                    reader.buffer.clear();
+                    return Some((Err(e.into()), reader));
+                }
+            }
+        }
+    })
+}
+
+/// Collects a token stream, reporting each text chunk to the progress sink of
+/// the request, if any
+pub async fn collect(mut tokens: TokenStream) -> Result<Generation, Box<dyn Error + Send + Sync>> {
+    let mut generation = Generation::default();
+    let mut chunks = 0;
+    while let Some(event) = tokens.next().await {
+        match event? {
+            StreamEvent::Text(text) => {
+                chunks += 1;
+                progress::report(&text, chunks);
+                generation.text.push_str(&text);
+            }
+            StreamEvent::Usage(usage) => {
+                let total = generation.usage.get_or_insert_with(Usage::default);
+                if usage.input_tokens > 0 {
+                    total.input_tokens = usage.input_tokens;
+                }
+                if usage.output_tokens > 0 {
+                    total.output_tokens = usage.output_tokens;
+                }
+            }
+            StreamEvent::Finished(reason) => generation.finish_reason = Some(reason),
+        }
+    }
+    Ok(generation)
+}
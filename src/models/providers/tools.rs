@@ -0,0 +1,48 @@
+// src/models/providers/tools.rs - Provider-agnostic tool (function) calling
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A function the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    /// Made of letters, digits, `_` and `-`, see [`tool_name`]
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments, an object
+    pub parameters: Value,
+}
+
+/// The call a model chose to make
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Name accepted by every provider for an identifier: at most 64 letters,
+/// digits, `_` and `-`; other characters are replaced by `_`
+pub fn tool_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_name() {
+        assert_eq!(tool_name("send_email"), "send_email");
+        assert_eq!(tool_name("calendar.create event"), "calendar_create_event");
+        assert_eq!(tool_name(&"a".repeat(80)).len(), 64);
+    }
+}
@@ -0,0 +1,351 @@
+// src/models/providers/truncation.rs - Recovers responses cut at max_tokens
+
+use super::error::ProviderError;
+use super::stream::TokenStream;
+use super::tools::{ToolCall, ToolDefinition};
+use super::{ModelConfig, ModelProvider};
+use crate::metrics;
+use crate::workflow::trace::{self, TraceEvent};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Shortest repeated text taken for an overlap when stitching a continuation
+const MIN_OVERLAP: usize = 8;
+
+/// What a step does when a response is cut at `max_tokens`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// Send the prompt again with twice the token budget
+    #[default]
+    Retry,
+    /// Ask the model to continue the cut text and stitch the pieces
+    Continue,
+    /// Fail the call
+    Fail,
+}
+
+fn default_max_attempts() -> u32 {
+    2
+}
+
+fn default_max_tokens_limit() -> u32 {
+    8192
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TruncationConfig {
+    #[serde(default)]
+    pub strategy: TruncationStrategy,
+    /// Retries or continuations after the first call
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Budget the retries don't go beyond
+    #[serde(default = "default_max_tokens_limit")]
+    pub max_tokens_limit: u32,
+}
+
+impl Default for TruncationConfig {
+    fn default() -> Self {
+        Self {
+            strategy: TruncationStrategy::default(),
+            max_attempts: default_max_attempts(),
+            max_tokens_limit: default_max_tokens_limit(),
+        }
+    }
+}
+
+fn continuation_prompt(prompt: &str, partial: &str) -> String {
+    format!(
+        "{}\n\nYour previous answer was cut off. This is what you wrote so far:\n{}\n\n\
+         Continue exactly where it stopped. Do not repeat any of it and do not add explanations.",
+        prompt, partial
+    )
+}
+
+/// Appends a continuation to the text it continues. Models often repeat the
+/// end of the cut text first; the repeated part is dropped.
+fn stitch(head: &str, tail: &str) -> String {
+    let longest = head.len().min(tail.len());
+    let overlap = (MIN_OVERLAP..=longest)
+        .rev()
+        .find(|&n| {
+            head.is_char_boundary(head.len() - n)
+                && tail.is_char_boundary(n)
+                && head.ends_with(&tail[..n])
+        })
+        .unwrap_or(0);
+    format!("{}{}", head, &tail[overlap..])
+}
+
+/// Wraps the provider of a step and handles its truncated responses as the
+/// step's [`TruncationConfig`] says
+pub struct TruncationGuard {
+    inner: Arc<dyn ModelProvider>,
+    config: TruncationConfig,
+}
+
+impl TruncationGuard {
+    pub fn new(inner: Arc<dyn ModelProvider>, config: TruncationConfig) -> Self {
+        Self { inner, config }
+    }
+}
+
+#[async_trait]
+impl ModelProvider for TruncationGuard {
+    async fn generate(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut current_prompt = prompt.to_string();
+        let mut current_model = model.clone();
+        let mut stitched = String::new();
+        let mut attempts = 0;
+
+        loop {
+            let error = match self.inner.generate(&current_prompt, &current_model).await {
+                Ok(text) if attempts == 0 => return Ok(text),
+                Ok(text) => {
+                    metrics::increment(
+                        &metrics::labeled(
+                            "llm_truncations_recovered",
+                            &[("model", &self.inner.model_name(model))],
+                        ),
+                        1,
+                    );
+                    return Ok(stitch(&stitched, &text));
+                }
+                Err(error) => error,
+            };
+            let Some(ProviderError::Truncated {
+                model: model_name,
+                max_tokens,
+                partial,
+            }) = error.downcast_ref::<ProviderError>()
+            else {
+                return Err(error);
+            };
+
+            let strategy = self.config.strategy;
+            metrics::increment(
+                &metrics::labeled(
+                    "llm_truncations",
+                    &[
+                        ("model", model_name),
+                        ("strategy", &format!("{:?}", strategy).to_lowercase()),
+                    ],
+                ),
+                1,
+            );
+            trace::record(TraceEvent::Truncated {
+                model: model_name.clone(),
+                max_tokens: *max_tokens,
+                strategy,
+            });
+
+            if attempts >= self.config.max_attempts {
+                return Err(error);
+            }
+            attempts += 1;
+
+            match strategy {
+                TruncationStrategy::Fail => return Err(error),
+                TruncationStrategy::Retry => {
+                    if current_model.max_tokens >= self.config.max_tokens_limit {
+                        warn!(
+                            "Output of {} is cut at the max_tokens limit ({})",
+                            model_name, self.config.max_tokens_limit
+                        );
+                        return Err(error);
+                    }
+                    current_model.max_tokens = current_model
+                        .max_tokens
+                        .saturating_mul(2)
+                        .min(self.config.max_tokens_limit);
+                    info!(
+                        "Output of {} was cut at {} tokens, retrying with {}",
+                        model_name, max_tokens, current_model.max_tokens
+                    );
+                }
+                TruncationStrategy::Continue => {
+                    stitched = stitch(&stitched, partial);
+                    current_prompt = continuation_prompt(prompt, &stitched);
+                    // The continuation is a fragment, it can't match the output format
+                    current_model.output = None;
+                    info!(
+                        "Output of {} was cut at {} tokens, asking it to continue",
+                        model_name, max_tokens
+                    );
+                }
+            }
+        }
+    }
+
+    // Text already streamed can't be taken back, so streams are passed through
+    // and a cut one ends with `FinishReason::Length` for its caller to handle
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+    ) -> Result<TokenStream, Box<dyn Error + Send + Sync>> {
+        self.inner.generate_stream(prompt, model).await
+    }
+
+    async fn call_tool(
+        &self,
+        prompt: &str,
+        model: &ModelConfig,
+        tools: &[ToolDefinition],
+    ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+        self.inner.call_tool(prompt, model, tools).await
+    }
+
+    fn model_name(&self, model: &ModelConfig) -> String {
+        self.inner.model_name(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::OutputFormat;
+    use std::sync::Mutex;
+
+    // Answers with the next canned response and records the calls it got
+    struct ScriptedProvider {
+        responses: Mutex<Vec<Result<String, ProviderError>>>,
+        calls: Mutex<Vec<(String, ModelConfig)>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(responses: Vec<Result<String, ProviderError>>) -> Arc<Self> {
+            Arc::new(Self {
+                responses: Mutex::new(responses),
+                calls: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl ModelProvider for ScriptedProvider {
+        async fn generate(
+            &self,
+            prompt: &str,
+            model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((prompt.to_string(), model.clone()));
+            Ok(self.responses.lock().unwrap().remove(0)?)
+        }
+    }
+
+    fn truncated(max_tokens: u32, partial: &str) -> Result<String, ProviderError> {
+        Err(ProviderError::Truncated {
+            model: "llama2".to_string(),
+            max_tokens,
+            partial: partial.to_string(),
+        })
+    }
+
+    fn model() -> ModelConfig {
+        ModelConfig {
+            max_tokens: 100,
+            ..Default::default()
+        }
+        .with_output(OutputFormat::Json)
+    }
+
+    #[test]
+    fn test_stitch_drops_repeated_text() {
+        assert_eq!(
+            stitch(r#"{"to": "alice@exa"#, r#"{"to": "alice@example.com"}"#),
+            r#"{"to": "alice@example.com"}"#
+        );
+        assert_eq!(
+            stitch(r#"{"to": "alice@exa"#, r#"mple.com"}"#),
+            r#"{"to": "alice@example.com"}"#
+        );
+        assert_eq!(stitch("", "abc"), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_retry_doubles_the_budget() {
+        let inner = ScriptedProvider::new(vec![
+            truncated(100, r#"{"to": "#),
+            Ok(r#"{"to": "Alice"}"#.to_string()),
+        ]);
+        let guard = TruncationGuard::new(inner.clone(), TruncationConfig::default());
+
+        let trace = trace::ExecutionTrace::default();
+        let response = trace.scope(guard.generate("hello", &model())).await;
+        assert_eq!(response.unwrap(), r#"{"to": "Alice"}"#);
+
+        let calls = inner.calls.lock().unwrap();
+        assert_eq!(calls[1].1.max_tokens, 200);
+        assert_eq!(calls[1].0, "hello");
+        assert_eq!(
+            trace.entries()[0].event,
+            TraceEvent::Truncated {
+                model: "llama2".to_string(),
+                max_tokens: 100,
+                strategy: TruncationStrategy::Retry,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_continue_stitches_the_pieces() {
+        let inner = ScriptedProvider::new(vec![
+            truncated(100, r#"{"to": "Alice", "subj"#),
+            truncated(100, r#"ect": "Quarterly"#),
+            Ok(r#" report"}"#.to_string()),
+        ]);
+        let config = TruncationConfig {
+            strategy: TruncationStrategy::Continue,
+            ..Default::default()
+        };
+        let guard = TruncationGuard::new(inner.clone(), config);
+
+        let response = guard.generate("hello", &model()).await.unwrap();
+        assert_eq!(
+            response,
+            r#"{"to": "Alice", "subject": "Quarterly report"}"#
+        );
+
+        let calls = inner.calls.lock().unwrap();
+        assert!(calls[2].0.starts_with("hello\n\n"));
+        assert!(calls[2]
+            .0
+            .contains(r#"{"to": "Alice", "subject": "Quarterly"#));
+        assert_eq!(calls[2].1.output, None);
+        assert_eq!(calls[2].1.max_tokens, 100);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_at_the_limit() {
+        let inner = ScriptedProvider::new(vec![truncated(100, "{"), truncated(150, "{")]);
+        let config = TruncationConfig {
+            max_tokens_limit: 150,
+            ..Default::default()
+        };
+        let guard = TruncationGuard::new(inner.clone(), config);
+
+        let error = guard.generate("hello", &model()).await.unwrap_err();
+        assert!(error.to_string().contains("cut at max_tokens (150)"));
+        assert_eq!(inner.calls.lock().unwrap().len(), 2);
+
+        // Other errors are not retried
+        let inner = ScriptedProvider::new(vec![Err(ProviderError::EmptyResponse {
+            provider: "Ollama".to_string(),
+        })]);
+        let guard = TruncationGuard::new(inner.clone(), TruncationConfig::default());
+        assert!(guard.generate("hello", &model()).await.is_err());
+        assert_eq!(inner.calls.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+// src/models/providers/usage.rs - Tokens consumed by model calls, recorded in the trace of the run
+
+use super::stream::Usage;
+use super::ModelConfig;
+use crate::workflow::trace::{self, TraceEvent};
+
+/// Tokens of `text` for providers that don't report usage, at about four
+/// characters per token
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+/// Records the tokens a call consumed in the current trace. Counts the
+/// provider didn't report (or reported as zero) are estimated from the
+/// prompt and the response.
+pub fn record(
+    model: &str,
+    prompt: &str,
+    config: &ModelConfig,
+    response: &str,
+    usage: Option<Usage>,
+) {
+    let usage = usage.unwrap_or_default();
+    let mut estimated = false;
+
+    let input_tokens = if usage.input_tokens > 0 {
+        usage.input_tokens
+    } else {
+        estimated = true;
+        estimate_tokens(prompt) + config.system.as_deref().map_or(0, estimate_tokens)
+    };
+    let output_tokens = if usage.output_tokens > 0 || response.is_empty() {
+        usage.output_tokens
+    } else {
+        estimated = true;
+        estimate_tokens(response)
+    };
+
+    trace::record(TraceEvent::TokenUsage {
+        model: model.to_string(),
+        input_tokens,
+        output_tokens,
+        estimated,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reported_and_estimated_counts() {
+        let trace = trace::ExecutionTrace::default();
+        trace
+            .scope(async {
+                let reported = Usage {
+                    input_tokens: 12,
+                    output_tokens: 5,
+                };
+                record(
+                    "claude-3-haiku",
+                    "hello",
+                    &ModelConfig::default(),
+                    "{}",
+                    Some(reported),
+                );
+                record("llama2", "12345678", &ModelConfig::default(), "abcde", None);
+            })
+            .await;
+
+        let events: Vec<TraceEvent> = trace.entries().into_iter().map(|e| e.event).collect();
+        assert_eq!(
+            events,
+            vec![
+                TraceEvent::TokenUsage {
+                    model: "claude-3-haiku".to_string(),
+                    input_tokens: 12,
+                    output_tokens: 5,
+                    estimated: false,
+                },
+                TraceEvent::TokenUsage {
+                    model: "llama2".to_string(),
+                    input_tokens: 2,
+                    output_tokens: 2,
+                    estimated: true,
+                },
+            ]
+        );
+    }
+}
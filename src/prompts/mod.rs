@@ -1,3 +1,4 @@
+use crate::utils::fingerprint::fingerprint;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::error::Error;
@@ -52,6 +53,32 @@ impl PromptManager {
         }
     }
 
+    /// Fingerprint of every prompt version, changes whenever a template is edited
+    pub fn versions_fingerprint(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        let mut names: Vec<&String> = self.config.prompts.keys().collect();
+        names.sort();
+        for name in names {
+            let prompt_versions = &self.config.prompts[name];
+            parts.push(format!("{}@{}", name, prompt_versions.default_version));
+            let mut versions: Vec<_> = prompt_versions.versions.iter().collect();
+            versions.sort_by_key(|(version, _)| *version);
+            for (version, prompt) in versions {
+                parts.push(format!("{}:{}", version, prompt.template));
+            }
+        }
+        fingerprint(&parts.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// Lists the versions available for a prompt
+    #[cfg(test)]
+    pub fn list_versions(&self, name: &str) -> Option<Vec<String>> {
+        let prompt_versions = self.config.prompts.get(name)?;
+        let mut versions: Vec<String> = prompt_versions.versions.keys().cloned().collect();
+        versions.sort();
+        Some(versions)
+    }
+
     pub fn format_find_endpoint(
         &self,
         input_sentence: &str,
@@ -67,6 +94,23 @@ impl PromptManager {
             .replace("{actions_list}", actions_list)
     }
 
+    pub fn format_extract_parameters(
+        &self,
+        sentence: &str,
+        endpoint_description: &str,
+        parameters: &str,
+        version: Option<&str>,
+    ) -> String {
+        let template = self
+            .get_prompt("extract_parameters", version)
+            .unwrap_or_default();
+
+        template
+            .replace("{sentence}", sentence)
+            .replace("{endpoint_description}", endpoint_description)
+            .replace("{parameters}", parameters)
+    }
+
     pub fn format_sentence_to_json(&self, sentence: &str, version: Option<&str>) -> String {
         let template = self
             .get_prompt("sentence_to_json", version)
@@ -74,6 +118,14 @@ impl PromptManager {
 
         template.replace("{sentence}", sentence)
     }
+
+    pub fn format_select_endpoint_tool(&self, sentence: &str, version: Option<&str>) -> String {
+        let template = self
+            .get_prompt("select_endpoint_tool", version)
+            .unwrap_or_default();
+
+        template.replace("{sentence}", sentence)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,379 @@
+// src/readiness.rs - Startup checks of the configured models, served by the GetReadiness RPC
+
+use crate::models::providers::{ProviderConfig, ProviderKind, ProviderRegistry};
+use crate::models::ModelsConfig;
+use crate::workflow::StepRouting;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_warmup_prompt() -> String {
+    "Reply with OK.".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReadinessConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Stop at startup when a model isn't available, instead of serving degraded
+    #[serde(default)]
+    pub fail_fast: bool,
+    /// Providers whose models answer a warmup prompt once checked, to load their weights
+    #[serde(default)]
+    pub warmup: Vec<String>,
+    #[serde(default = "default_warmup_prompt")]
+    pub warmup_prompt: String,
+    /// Limit on each check and each warmup call
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            fail_fast: false,
+            warmup: Vec::new(),
+            warmup_prompt: default_warmup_prompt(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// Whether a provider can serve one of the configured models
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCheck {
+    pub provider: String,
+    pub model: String,
+    pub ready: bool,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub warmed_up: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadinessStatus {
+    /// The checks haven't run, or are disabled
+    Unchecked,
+    Ready,
+    /// Some models are unavailable; requests using them will fail
+    Degraded,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadinessReport {
+    pub status: ReadinessStatus,
+    pub checks: Vec<ModelCheck>,
+}
+
+impl ReadinessReport {
+    fn from_checks(checks: Vec<ModelCheck>) -> Self {
+        let status = if checks.iter().all(|check| check.ready) {
+            ReadinessStatus::Ready
+        } else {
+            ReadinessStatus::Degraded
+        };
+        Self { status, checks }
+    }
+
+    pub fn failures(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .filter(|check| !check.ready)
+            .map(|check| {
+                format!(
+                    "{} ({}): {}",
+                    check.provider,
+                    check.model,
+                    check.error.as_deref().unwrap_or("not ready")
+                )
+            })
+            .collect()
+    }
+}
+
+fn state() -> &'static Mutex<ReadinessReport> {
+    static STATE: OnceLock<Mutex<ReadinessReport>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(ReadinessReport {
+            status: ReadinessStatus::Unchecked,
+            checks: Vec::new(),
+        })
+    })
+}
+
+pub fn current() -> ReadinessReport {
+    state().lock().unwrap().clone()
+}
+
+pub fn publish(report: ReadinessReport) {
+    *state().lock().unwrap() = report;
+}
+
+/// Provider and `models` entry of each model call the workflow steps make,
+/// failover chains expanded into their members, in a stable order
+pub fn targets(
+    routings: &[StepRouting],
+    default_provider: &str,
+    providers: &HashMap<String, ProviderConfig>,
+) -> Vec<(String, String)> {
+    let mut targets = BTreeSet::new();
+    for routing in routings {
+        let name = routing.provider.as_deref().unwrap_or(default_provider);
+        match providers.get(name) {
+            Some(config) if config.kind == Some(ProviderKind::Failover) => {
+                for member in &config.chain {
+                    targets.insert((member.clone(), routing.model.clone()));
+                }
+            }
+            _ => {
+                targets.insert((name.to_string(), routing.model.clone()));
+            }
+        }
+    }
+    targets.into_iter().collect()
+}
+
+fn not_ready(provider: &str, model: &str, error: String) -> ModelCheck {
+    ModelCheck {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        ready: false,
+        error: Some(error),
+        latency_ms: 0,
+        warmed_up: false,
+    }
+}
+
+/// Checks the model of each target, a provider name and an entry of the
+/// `models` section, and warms up those configured to
+pub async fn check_models(
+    providers: &ProviderRegistry,
+    models: &ModelsConfig,
+    targets: &[(String, String)],
+    config: &ReadinessConfig,
+) -> ReadinessReport {
+    let limit = Duration::from_secs(config.timeout_secs);
+    let mut checks = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (name, entry) in targets {
+        let provider = match providers.resolve(Some(name)) {
+            Ok(provider) => provider,
+            Err(e) => {
+                warn!("Model {} of {} is not ready: {}", entry, name, e);
+                checks.push(not_ready(name, entry, e.to_string()));
+                continue;
+            }
+        };
+        let model = match models.get(entry) {
+            Ok(model) => model,
+            Err(e) => {
+                warn!("Model {} of {} is not ready: {}", entry, name, e);
+                checks.push(not_ready(name, entry, e.to_string()));
+                continue;
+            }
+        };
+        let model_name = provider.model_name(model);
+        if model_name.is_empty() {
+            let error = format!("the {} entry of `models` has no model for {}", entry, name);
+            warn!("Model {} of {} is not ready: {}", entry, name, error);
+            checks.push(not_ready(name, entry, error));
+            continue;
+        }
+        if !seen.insert((name.clone(), model_name.clone())) {
+            continue;
+        }
+
+        let started = Instant::now();
+        let error = match timeout(limit, provider.check_model(model)).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!("no answer within {}s", config.timeout_secs)),
+        };
+        let mut check = ModelCheck {
+            provider: name.to_string(),
+            model: model_name,
+            ready: error.is_none(),
+            error,
+            latency_ms: started.elapsed().as_millis() as u64,
+            warmed_up: false,
+        };
+
+        if check.ready && config.warmup.iter().any(|warm| warm == name) {
+            match timeout(limit, provider.generate(&config.warmup_prompt, model)).await {
+                Ok(Ok(_)) => check.warmed_up = true,
+                Ok(Err(e)) => warn!("Warmup of {} ({}) failed: {}", name, check.model, e),
+                Err(_) => warn!("Warmup of {} ({}) timed out", name, check.model),
+            }
+        }
+
+        match &check.error {
+            None => info!(
+                "Model {} of {} is ready ({} ms{})",
+                check.model,
+                name,
+                check.latency_ms,
+                if check.warmed_up { ", warmed up" } else { "" }
+            ),
+            Some(e) => warn!("Model {} of {} is not ready: {}", check.model, name, e),
+        }
+        checks.push(check);
+    }
+
+    ReadinessReport::from_checks(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::{ModelConfig, ModelProvider};
+    use async_trait::async_trait;
+    use std::error::Error;
+    use std::sync::Arc;
+
+    // Has every model except `missing`, and counts its generate calls
+    struct PartialProvider(Mutex<u32>);
+
+    #[async_trait]
+    impl ModelProvider for PartialProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            *self.0.lock().unwrap() += 1;
+            Ok("OK".to_string())
+        }
+
+        async fn check_model(
+            &self,
+            model: &ModelConfig,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if model.ollama == "missing" {
+                return Err("model 'missing' not found".into());
+            }
+            Ok(())
+        }
+
+        fn model_name(&self, model: &ModelConfig) -> String {
+            model.ollama.clone()
+        }
+    }
+
+    // A provider whose host is down
+    struct DownProvider;
+
+    #[async_trait]
+    impl ModelProvider for DownProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            Err("no host is reachable".into())
+        }
+    }
+
+    fn routing(model: &str, provider: Option<&str>) -> StepRouting {
+        StepRouting {
+            model: model.to_string(),
+            provider: provider.map(str::to_string),
+            hedge: None,
+            truncation: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_and_warm_up_configured_models() {
+        let models: ModelsConfig = serde_yaml::from_str(
+            "
+            sentence_to_json: {ollama: llama2, temperature: 0.1, max_tokens: 10}
+            find_endpoint: {ollama: llama2, temperature: 0.1, max_tokens: 10}
+            semantic_match: {ollama: missing, temperature: 0.1, max_tokens: 10}
+            claude_only: {claude: claude-3-haiku, temperature: 0.1, max_tokens: 10}
+            ",
+        )
+        .unwrap();
+        let provider = Arc::new(PartialProvider(Mutex::new(0)));
+        let mut registry = ProviderRegistry::new("ollama");
+        registry.register("ollama", provider.clone());
+        let config = ReadinessConfig {
+            warmup: vec!["ollama".to_string()],
+            ..Default::default()
+        };
+
+        let routings = [
+            routing("sentence_to_json", None),
+            routing("find_endpoint", None),
+            routing("semantic_match", Some("ollama")),
+        ];
+        let checked = targets(&routings, "ollama", &HashMap::new());
+        let report = check_models(&registry, &models, &checked, &config).await;
+        assert_eq!(report.status, ReadinessStatus::Degraded);
+        // Each model name is checked once; entries no step uses are skipped
+        let checked: Vec<(&str, bool)> = report
+            .checks
+            .iter()
+            .map(|check| (check.model.as_str(), check.ready))
+            .collect();
+        assert_eq!(checked, vec![("llama2", true), ("missing", false)]);
+        assert!(report.checks[0].warmed_up);
+        assert_eq!(*provider.0.lock().unwrap(), 1);
+        assert_eq!(
+            report.failures(),
+            vec!["ollama (missing): model 'missing' not found".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_the_providers_of_the_steps_are_checked() {
+        let models: ModelsConfig = serde_yaml::from_str(
+            "
+            find_endpoint: {ollama: llama2, temperature: 0.1, max_tokens: 10}
+            semantic_match: {ollama: mistral, temperature: 0.1, max_tokens: 10}
+            ",
+        )
+        .unwrap();
+        let mut registry = ProviderRegistry::new("claude");
+        registry.register("claude", Arc::new(PartialProvider(Mutex::new(0))));
+        registry.register("ollama", Arc::new(DownProvider));
+
+        // Ollama is registered but no step routes to it
+        let routings = [
+            routing("find_endpoint", None),
+            routing("semantic_match", None),
+        ];
+        let checked = targets(&routings, "claude", &HashMap::new());
+        let report = check_models(&registry, &models, &checked, &Default::default()).await;
+        assert_eq!(report.status, ReadinessStatus::Ready);
+        assert_eq!(report.checks.len(), 2);
+
+        // Failover chains are checked through their members
+        let providers: HashMap<String, ProviderConfig> = serde_yaml::from_str(
+            "
+            resilient: {enabled: true, type: failover, chain: [claude, ollama]}
+            ",
+        )
+        .unwrap();
+        let routings = [routing("find_endpoint", Some("resilient"))];
+        assert_eq!(
+            targets(&routings, "claude", &providers),
+            vec![
+                ("claude".to_string(), "find_endpoint".to_string()),
+                ("ollama".to_string(), "find_endpoint".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// src/reasoning.rs - Strips reasoning sections and code fences from model answers before parsing
+
+use crate::workflow::trace::{self, TraceEvent};
+use regex::Regex;
+use std::sync::OnceLock;
+use tracing::debug;
+
+// Tags reasoning models (deepseek-r1, qwq, ...) wrap their reasoning in
+const TAGS: &str = "think|thinking|reasoning";
+
+fn closed_block() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"(?is)<({TAGS})>(.*?)</({TAGS})>")).unwrap())
+}
+
+// Chat templates that open the block themselves leave only its end in the answer
+fn stray_close() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"(?is)^(.*?)</({TAGS})>")).unwrap())
+}
+
+// A block the model never closed, e.g. when it was cut at max_tokens
+fn unclosed_open() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"(?is)<({TAGS})>(.*)$")).unwrap())
+}
+
+fn fenced_block() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?s)```[\w+-]*[ \t]*\r?\n(.*?)```").unwrap())
+}
+
+/// Splits `text` into the answer and the reasoning sections around it
+pub fn strip_reasoning(text: &str) -> (String, Vec<String>) {
+    let mut reasoning = Vec::new();
+
+    let mut answer = closed_block()
+        .replace_all(text, |captures: &regex::Captures| {
+            reasoning.push(captures[2].trim().to_string());
+            ""
+        })
+        .to_string();
+    if let Some(captures) = stray_close().captures(&answer) {
+        reasoning.push(captures[1].trim().to_string());
+        answer = answer[captures[0].len()..].to_string();
+    }
+    if let Some(captures) = unclosed_open().captures(&answer) {
+        reasoning.push(captures[2].trim().to_string());
+        answer = answer[..captures.get(0).unwrap().start()].to_string();
+    }
+
+    reasoning.retain(|section| !section.is_empty());
+    (answer.trim().to_string(), reasoning)
+}
+
+/// The content of the first fenced code block of `text`, or `text` without
+/// the opening fence of a block that was never closed
+pub fn strip_code_fences(text: &str) -> String {
+    if let Some(captures) = fenced_block().captures(text) {
+        return captures[1].trim().to_string();
+    }
+    let trimmed = text.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => rest
+            .split_once('\n')
+            .map_or("", |(_, body)| body)
+            .trim()
+            .to_string(),
+        None => trimmed.to_string(),
+    }
+}
+
+/// The answer of a model response, its reasoning recorded in the current trace
+pub fn without_reasoning(text: &str) -> String {
+    let (answer, reasoning) = strip_reasoning(text);
+    if !reasoning.is_empty() {
+        let reasoning = reasoning.join("\n\n");
+        debug!("Stripped {} characters of reasoning", reasoning.len());
+        trace::record(TraceEvent::Reasoning { text: reasoning });
+    }
+    answer
+}
+
+/// The answer of a model response without reasoning nor code fence, ready to parse
+pub fn clean_response(text: &str) -> String {
+    strip_code_fences(&without_reasoning(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_think_blocks() {
+        let response =
+            "<think>\nThe user wants {\"to\": ...} so I pick\nsend email\n</think>\n\nsend email";
+        let (answer, reasoning) = strip_reasoning(response);
+        assert_eq!(answer, "send email");
+        assert_eq!(
+            reasoning,
+            vec!["The user wants {\"to\": ...} so I pick\nsend email".to_string()]
+        );
+
+        // Only the closing tag, and a block cut before it was closed
+        let (answer, reasoning) = strip_reasoning("Okay, {maybe}.\n</think>\n{\"a\": 1}");
+        assert_eq!(answer, "{\"a\": 1}");
+        assert_eq!(reasoning, vec!["Okay, {maybe}.".to_string()]);
+        let (answer, reasoning) = strip_reasoning("<THINKING>Let me see, {");
+        assert_eq!(answer, "");
+        assert_eq!(reasoning, vec!["Let me see, {".to_string()]);
+
+        assert_eq!(strip_reasoning("plain").0, "plain");
+    }
+
+    #[test]
+    fn test_strips_code_fences() {
+        assert_eq!(
+            strip_code_fences("Here it is:\n```json\n{\"a\": 1}\n```\nDone."),
+            "{\"a\": 1}"
+        );
+        assert_eq!(strip_code_fences("```\nsend email\n```"), "send email");
+        assert_eq!(strip_code_fences("```json\n{\"a\": "), "{\"a\":");
+        assert_eq!(strip_code_fences("{\"a\": 1}"), "{\"a\": 1}");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_kept_in_the_trace() {
+        let trace = trace::ExecutionTrace::default();
+        let answer = trace
+            .scope(async {
+                clean_response("<think>{\"draft\": true}</think>\n```json\n{\"a\": 1}\n```")
+            })
+            .await;
+        assert_eq!(answer, "{\"a\": 1}");
+        assert_eq!(
+            trace.entries()[0].event,
+            TraceEvent::Reasoning {
+                text: "{\"draft\": true}".to_string()
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
 // src/sentence_service.rs
 use crate::analyze_sentence::analyze_sentence;
-use crate::models::providers::ModelProvider;
+use crate::models::providers::ProviderRegistry;
 use futures::Stream;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -12,23 +12,32 @@ pub mod sentence {
     tonic::include_proto!("sentence");
 }
 
+use crate::models::provenance::{Provenance, ValueSource};
+use crate::readiness::{self, ReadinessStatus};
+use crate::usage::{record_totals, RunUsage};
+use crate::workflow::progress::{Progress, ProgressSink};
+use crate::workflow::trace::TraceEntry;
 use sentence::sentence_service_server::SentenceService;
-use sentence::{Parameter, SentenceRequest, SentenceResponse};
+use sentence::{
+    MetricsRequest, MetricsResponse, ModelCheck, Parameter, ReadinessRequest, ReadinessResponse,
+    SentenceRequest, SentenceResponse,
+};
 use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
 use tracing::Instrument;
 
 pub struct SentenceAnalyzeService {
-    provider: Arc<dyn ModelProvider>,
+    providers: Arc<ProviderRegistry>,
     api_url: Option<String>,
 }
 
 impl SentenceAnalyzeService {
-    // Add a constructor to store the provider and API URL
-    pub fn new(provider: Arc<dyn ModelProvider>, api_url: Option<String>) -> Self {
-        Self { provider, api_url }
+    // Add a constructor to store the providers and API URL
+    pub fn new(providers: Arc<ProviderRegistry>, api_url: Option<String>) -> Self {
+        Self { providers, api_url }
     }
 
     // Get email from metadata with validation
+    #[allow(clippy::result_large_err)]
     fn get_email_validated(&self, metadata: &MetadataMap) -> Result<String, tonic::Status> {
         let email = metadata
             .get("email")
@@ -60,11 +69,98 @@ impl SentenceAnalyzeService {
     }
 }
 
+// Converts a JSON value into its protobuf `google.protobuf.Value` counterpart
+fn json_to_prost_value(value: &serde_json::Value) -> prost_types::Value {
+    use prost_types::value::Kind;
+
+    let kind = match value {
+        serde_json::Value::Null => Kind::NullValue(prost_types::NullValue::NullValue as i32),
+        serde_json::Value::Bool(b) => Kind::BoolValue(*b),
+        serde_json::Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        serde_json::Value::String(s) => Kind::StringValue(s.clone()),
+        serde_json::Value::Array(items) => Kind::ListValue(prost_types::ListValue {
+            values: items.iter().map(json_to_prost_value).collect(),
+        }),
+        serde_json::Value::Object(map) => Kind::StructValue(prost_types::Struct {
+            fields: map
+                .iter()
+                .map(|(k, v)| (k.clone(), json_to_prost_value(v)))
+                .collect(),
+        }),
+    };
+
+    prost_types::Value { kind: Some(kind) }
+}
+
+fn provenance_to_proto(provenance: &Provenance) -> sentence::Provenance {
+    let source = match provenance.source {
+        ValueSource::Exact => sentence::ValueSource::Exact,
+        ValueSource::Alias => sentence::ValueSource::Alias,
+        ValueSource::Semantic => sentence::ValueSource::Semantic,
+        ValueSource::Extractor => sentence::ValueSource::Extractor,
+        ValueSource::Default => sentence::ValueSource::Default,
+        ValueSource::Session => sentence::ValueSource::Session,
+        ValueSource::UserProvided => sentence::ValueSource::UserProvided,
+    };
+
+    sentence::Provenance {
+        source: source as i32,
+        confidence: provenance.confidence,
+        span: provenance.span.map(|span| sentence::TextSpan {
+            start: span.start as u32,
+            end: span.end as u32,
+        }),
+        field: provenance.field.clone(),
+        verified: provenance.verified,
+    }
+}
+
+// Flattens a trace entry into its kind and the remaining fields as a protobuf Struct
+fn trace_entry_to_proto(entry: &TraceEntry) -> sentence::TraceEvent {
+    let mut data = match serde_json::to_value(&entry.event) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let kind = match data.remove("kind") {
+        Some(serde_json::Value::String(kind)) => kind,
+        _ => String::new(),
+    };
+
+    sentence::TraceEvent {
+        step: entry.step.clone().unwrap_or_default(),
+        kind,
+        data: Some(prost_types::Struct {
+            fields: data
+                .iter()
+                .map(|(k, v)| (k.clone(), json_to_prost_value(v)))
+                .collect(),
+        }),
+    }
+}
+
+fn progress_to_proto(progress: Progress) -> sentence::Progress {
+    sentence::Progress {
+        step: progress.step.unwrap_or_default(),
+        text: progress.text,
+        chunks: progress.chunks,
+    }
+}
+
+fn usage_to_proto(usage: &RunUsage) -> sentence::Usage {
+    sentence::Usage {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cost_usd: usage.cost_usd,
+        estimated: usage.estimated,
+        unpriced_models: usage.unpriced_models.clone(),
+    }
+}
+
 // Implement Debug manually
 impl std::fmt::Debug for SentenceAnalyzeService {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SentenceAnalyzeService")
-            .field("provider", &"<dyn ModelProvider>")
+            .field("providers", &self.providers)
             .field("api_url", &self.api_url)
             .finish()
     }
@@ -75,6 +171,42 @@ impl SentenceService for SentenceAnalyzeService {
     type AnalyzeSentenceStream =
         Pin<Box<dyn Stream<Item = Result<SentenceResponse, Status>> + Send>>;
 
+    async fn get_metrics(
+        &self,
+        _request: Request<MetricsRequest>,
+    ) -> Result<Response<MetricsResponse>, Status> {
+        Ok(Response::new(MetricsResponse {
+            counters: crate::metrics::snapshot().into_iter().collect(),
+        }))
+    }
+
+    async fn get_readiness(
+        &self,
+        _request: Request<ReadinessRequest>,
+    ) -> Result<Response<ReadinessResponse>, Status> {
+        let report = readiness::current();
+        let status = match report.status {
+            ReadinessStatus::Unchecked => sentence::ReadinessStatus::Unchecked,
+            ReadinessStatus::Ready => sentence::ReadinessStatus::Ready,
+            ReadinessStatus::Degraded => sentence::ReadinessStatus::Degraded,
+        };
+        Ok(Response::new(ReadinessResponse {
+            status: status as i32,
+            checks: report
+                .checks
+                .into_iter()
+                .map(|check| ModelCheck {
+                    provider: check.provider,
+                    model: check.model,
+                    ready: check.ready,
+                    error: check.error,
+                    latency_ms: check.latency_ms,
+                    warmed_up: check.warmed_up,
+                })
+                .collect(),
+        }))
+    }
+
     #[tracing::instrument(skip(self, request), fields(client_id, email))]
     async fn analyze_sentence(
         &self,
@@ -95,7 +227,8 @@ impl SentenceService for SentenceAnalyzeService {
             }
         };
 
-        let input_sentence = request.into_inner().sentence;
+        let request = request.into_inner();
+        let input_sentence = request.sentence;
         tracing::info!(
             input_sentence = %input_sentence,
             email = %email,
@@ -122,20 +255,43 @@ impl SentenceService for SentenceAnalyzeService {
             email = %email
         );
 
-        // Clone the provider and API URL to move into the spawned task
-        let provider_clone = self.provider.clone();
+        // Clone the providers and API URL to move into the spawned task
+        let providers_clone = self.providers.clone();
         let api_url_clone = self.api_url.clone();
 
         tokio::spawn(async move {
-            // Pass the input_sentence, provider, API URL, and email to analyze_sentence
-            let result = analyze_sentence(
+            // Pass the input_sentence, providers, API URL, and email to analyze_sentence
+            let analysis = analyze_sentence(
                 &input_sentence,
-                provider_clone,
+                providers_clone,
                 api_url_clone.clone(),
                 &email,
             )
-            .instrument(analyze_span)
-            .await;
+            .instrument(analyze_span);
+
+            let result = if request.stream_progress {
+                let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+                let progress_stream = tx.clone();
+                let forwarder = tokio::spawn(async move {
+                    while let Some(progress) = progress_rx.recv().await {
+                        let response = SentenceResponse {
+                            progress: Some(progress_to_proto(progress)),
+                            ..Default::default()
+                        };
+                        // The client went away; the analysis still completes
+                        if progress_stream.send(Ok(response)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                let result = ProgressSink::new(progress_tx).scope(analysis).await;
+                // The sink is dropped with the scope, so the forwarder drains and
+                // the final response follows every progress message
+                let _ = forwarder.await;
+                result
+            } else {
+                analysis.await
+            };
 
             match result {
                 Ok(result) => {
@@ -145,6 +301,11 @@ impl SentenceService for SentenceAnalyzeService {
                         "Analysis completed"
                     );
 
+                    // Cached results didn't call any model this time
+                    if !result.cached {
+                        record_totals(&email, &client_id, &result.usage);
+                    }
+
                     let response = SentenceResponse {
                         endpoint_id: result.endpoint_id,
                         endpoint_description: result.endpoint_description,
@@ -155,6 +316,8 @@ impl SentenceService for SentenceAnalyzeService {
                                 name: param.name,
                                 description: param.description,
                                 semantic_value: param.semantic_value,
+                                value: param.value.as_ref().map(json_to_prost_value),
+                                provenance: param.provenance.as_ref().map(provenance_to_proto),
                             })
                             .collect(),
                         json_output: match serde_json::to_string(&result.json_output) {
@@ -164,6 +327,11 @@ impl SentenceService for SentenceAnalyzeService {
                                 format!("{{\"error\": \"JSON serialization failed: {}\"}}", e)
                             }
                         },
+                        trace: result.trace.iter().map(trace_entry_to_proto).collect(),
+                        escalations: result.escalations,
+                        cached: result.cached,
+                        progress: None,
+                        usage: Some(usage_to_proto(&result.usage)),
                     };
 
                     tracing::info!(
@@ -0,0 +1,176 @@
+// src/usage.rs - Token usage and cost of an analysis, and cumulative totals per tenant and client
+
+use crate::metrics;
+use crate::workflow::trace::{TraceEntry, TraceEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct ModelPrice {
+    #[serde(default)]
+    pub input_per_million: f64,
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+/// Prices keyed by model name. A key also prices the models it is a prefix
+/// of, so `claude-3-haiku` covers `claude-3-haiku-20240307`.
+pub type PriceTable = HashMap<String, ModelPrice>;
+
+fn price_of<'a>(prices: &'a PriceTable, model: &str) -> Option<&'a ModelPrice> {
+    prices.get(model).or_else(|| {
+        prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| price)
+    })
+}
+
+/// Tokens and cost of the model calls of one run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Some counts were estimated because the provider didn't report them
+    pub estimated: bool,
+    /// Models without an entry in the price table, counted at no cost
+    pub unpriced_models: Vec<String>,
+}
+
+impl RunUsage {
+    /// Sums the `token_usage` events of a run's trace
+    pub fn from_trace(entries: &[TraceEntry], prices: &PriceTable) -> Self {
+        let mut usage = RunUsage::default();
+        for entry in entries {
+            let TraceEvent::TokenUsage {
+                model,
+                input_tokens,
+                output_tokens,
+                estimated,
+            } = &entry.event
+            else {
+                continue;
+            };
+
+            usage.input_tokens += *input_tokens as u64;
+            usage.output_tokens += *output_tokens as u64;
+            usage.estimated |= *estimated;
+            match price_of(prices, model) {
+                Some(price) => {
+                    usage.cost_usd += (*input_tokens as f64 * price.input_per_million
+                        + *output_tokens as f64 * price.output_per_million)
+                        / 1_000_000.0
+                }
+                None if !usage.unpriced_models.contains(model) => {
+                    usage.unpriced_models.push(model.clone())
+                }
+                None => {}
+            }
+        }
+        usage
+    }
+}
+
+/// Adds a run to the cumulative counters of its tenant (email) and client,
+/// which quotas and reports read from the metrics. Cost is counted in
+/// millionths of a dollar.
+pub fn record_totals(email: &str, client_id: &str, usage: &RunUsage) {
+    let email = email.trim().to_lowercase();
+    for label in [("email", email.as_str()), ("client_id", client_id)] {
+        let labels = [label];
+        metrics::increment(
+            &metrics::labeled("usage_input_tokens", &labels),
+            usage.input_tokens,
+        );
+        metrics::increment(
+            &metrics::labeled("usage_output_tokens", &labels),
+            usage.output_tokens,
+        );
+        metrics::increment(
+            &metrics::labeled("usage_cost_microusd", &labels),
+            (usage.cost_usd * 1_000_000.0).round() as u64,
+        );
+        metrics::increment(&metrics::labeled("usage_analyses", &labels), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage_entry(model: &str, input_tokens: u32, output_tokens: u32) -> TraceEntry {
+        TraceEntry {
+            step: Some("json_generation".to_string()),
+            event: TraceEvent::TokenUsage {
+                model: model.to_string(),
+                input_tokens,
+                output_tokens,
+                estimated: model == "llama2",
+            },
+        }
+    }
+
+    #[test]
+    fn test_run_usage_and_cost() {
+        let prices = PriceTable::from([
+            (
+                "claude-3".to_string(),
+                ModelPrice {
+                    input_per_million: 1.0,
+                    output_per_million: 1.0,
+                },
+            ),
+            (
+                "claude-3-haiku".to_string(),
+                ModelPrice {
+                    input_per_million: 0.25,
+                    output_per_million: 1.25,
+                },
+            ),
+        ]);
+        let entries = vec![
+            usage_entry("claude-3-haiku-20240307", 2000, 400),
+            usage_entry("claude-3-haiku", 2000, 400),
+            usage_entry("llama2", 300, 50),
+            TraceEntry {
+                step: None,
+                event: TraceEvent::StepCompleted { duration_ms: 5 },
+            },
+        ];
+
+        let usage = RunUsage::from_trace(&entries, &prices);
+        assert_eq!((usage.input_tokens, usage.output_tokens), (4300, 850));
+        // The longest matching prefix prices the dated model name
+        assert!((usage.cost_usd - 0.002).abs() < 1e-12);
+        assert!(usage.estimated);
+        assert_eq!(usage.unpriced_models, vec!["llama2".to_string()]);
+    }
+
+    #[test]
+    fn test_totals_per_email_and_client() {
+        let usage = RunUsage {
+            input_tokens: 100,
+            output_tokens: 20,
+            cost_usd: 0.0015,
+            ..Default::default()
+        };
+        record_totals("Totals@Example.com", "totals-client", &usage);
+        record_totals("totals@example.com", "totals-client", &usage);
+
+        let counters = metrics::snapshot();
+        let email = [("email", "totals@example.com")];
+        let client = [("client_id", "totals-client")];
+        assert_eq!(
+            counters[&metrics::labeled("usage_input_tokens", &email)],
+            200
+        );
+        assert_eq!(
+            counters[&metrics::labeled("usage_cost_microusd", &client)],
+            3000
+        );
+        assert_eq!(counters[&metrics::labeled("usage_analyses", &client)], 2);
+    }
+}
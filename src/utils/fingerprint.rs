@@ -0,0 +1,32 @@
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of the parts, as 64 hex characters. Stable across runs, unlike
+/// `DefaultHasher`, so it can name files on disk, and collision-resistant, so
+/// it can stand for keys built from user input. Parts are separated so that
+/// `["ab", "c"]` and `["a", "bc"]` differ.
+pub fn fingerprint(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // Never part of UTF-8 text
+        hasher.update([0xff]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(&["hello"]), fingerprint(&["hello"]));
+        assert_eq!(fingerprint(&["hello"]).len(), 64);
+        assert_ne!(fingerprint(&["ab", "c"]), fingerprint(&["a", "bc"]));
+        assert_ne!(fingerprint(&["hello"]), fingerprint(&["hellp"]));
+    }
+}
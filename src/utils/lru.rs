@@ -0,0 +1,54 @@
+// Least recently used map, shared by the response and analysis caches
+
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug)]
+pub struct Lru<V> {
+    entries: HashMap<String, (V, u64)>,
+    // Last use tick -> key, the first entry is the least recently used
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl<V> Default for Lru<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<V: Clone> Lru<V> {
+    pub fn get(&mut self, key: &str) -> Option<V> {
+        self.tick += 1;
+        let (entry, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        Some(entry.clone())
+    }
+
+    /// Inserts an entry, evicting the least recently used ones beyond `capacity`
+    pub fn insert(&mut self, key: String, entry: V, capacity: usize) {
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        }
+        while self.entries.len() >= capacity.max(1) {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (entry, self.tick));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some((_, used)) = self.entries.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+}
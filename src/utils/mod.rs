@@ -1 +1,4 @@
 pub mod email;
+pub mod fingerprint;
+pub mod lru;
+pub mod text_span;
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Location of a value in the original sentence, in character (not byte) offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Lowercases one character into one character so offsets stay aligned with the original text
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// Whether the characters on either side of a match belong to different words;
+// values that start or end with punctuation bound themselves
+fn is_word_boundary(before: Option<&char>, after: Option<&char>) -> bool {
+    match (before, after) {
+        (Some(before), Some(after)) => !(before.is_alphanumeric() && after.is_alphanumeric()),
+        _ => true,
+    }
+}
+
+/// Finds the first case-insensitive occurrence of `needle` in `haystack` that
+/// starts and ends on word boundaries, so "12" isn't found in "2012"
+pub fn locate_span(haystack: &str, needle: &str) -> Option<TextSpan> {
+    let needle: Vec<char> = needle.trim().chars().map(lowercase).collect();
+    if needle.is_empty() {
+        return None;
+    }
+
+    let chars: Vec<char> = haystack.chars().map(lowercase).collect();
+
+    (0..=chars.len().saturating_sub(needle.len()))
+        .find(|&start| {
+            let end = start + needle.len();
+            end <= chars.len()
+                && chars[start..end] == needle[..]
+                && is_word_boundary(start.checked_sub(1).map(|i| &chars[i]), needle.first())
+                && is_word_boundary(needle.last(), chars.get(end))
+        })
+        .map(|start| TextSpan {
+            start,
+            end: start + needle.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_span() {
+        let sentence = "Send the report to Alice at alice@example.com";
+        assert_eq!(
+            locate_span(sentence, "alice@example.com"),
+            Some(TextSpan { start: 28, end: 45 })
+        );
+        assert_eq!(
+            locate_span(sentence, "REPORT"),
+            Some(TextSpan { start: 9, end: 15 })
+        );
+        assert_eq!(locate_span(sentence, "bob"), None);
+        assert_eq!(locate_span(sentence, "  "), None);
+    }
+
+    #[test]
+    fn test_locate_span_on_word_boundaries() {
+        assert_eq!(locate_span("Budget for 2012", "12"), None);
+        assert_eq!(locate_span("Call Diane", "an"), None);
+        assert_eq!(
+            locate_span("In 2012, book 12 seats", "12"),
+            Some(TextSpan { start: 14, end: 16 })
+        );
+        assert_eq!(
+            locate_span("Email (alice@example.com)", "alice@example.com"),
+            Some(TextSpan { start: 7, end: 24 })
+        );
+    }
+
+    #[test]
+    fn test_locate_span_uses_character_offsets() {
+        let sentence = "Réunion avec Zoé demain";
+        assert_eq!(
+            locate_span(sentence, "zoé"),
+            Some(TextSpan { start: 13, end: 16 })
+        );
+    }
+}
@@ -1,15 +1,18 @@
 use std::error::Error;
 
+use crate::reasoning;
 use tracing::{debug, error};
 
 pub async fn extract_matched_action(ollama_response: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
     debug!("Extracting matched action from response");
 
+    // Reasoning models think out loud before answering
+    let answer = reasoning::clean_response(ollama_response);
+
     // Get the last non-empty line from the response
-    let last_line = ollama_response
+    let last_line = answer
         .lines()
-        .filter(|line| !line.trim().is_empty())
-        .last()
+        .rfind(|line| !line.trim().is_empty())
         .ok_or_else(|| {
             error!("No valid lines found in response");
             "Empty response"
@@ -0,0 +1,190 @@
+use crate::json_helper::sanitize_json;
+use crate::models::providers::{ModelConfig, ModelProvider, OutputFormat};
+use crate::models::Endpoint;
+use crate::prompts::PromptManager;
+use serde_json::{json, Map, Value};
+use std::{error::Error, sync::Arc};
+use tracing::{debug, info};
+
+// Renders the endpoint parameters as the slot list given to the model
+fn format_parameter_schema(endpoint: &Endpoint) -> String {
+    endpoint
+        .parameters
+        .iter()
+        .map(|p| {
+            format!(
+                "- {} ({}, {}): {}",
+                p.name,
+                p.param_type.as_deref().unwrap_or("string"),
+                if p.required.unwrap_or(false) {
+                    "required"
+                } else {
+                    "optional"
+                },
+                p.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// JSON schema of the expected answer: one property per parameter, null when
+// the sentence doesn't provide it
+pub fn output_schema(endpoint: &Endpoint) -> Value {
+    let properties: Map<String, Value> = endpoint
+        .parameters
+        .iter()
+        .map(|p| {
+            let json_type = match p.param_type.as_deref().map(str::to_lowercase).as_deref() {
+                Some("number") | Some("float") => "number",
+                Some("integer") | Some("int") => "integer",
+                Some("boolean") | Some("bool") => "boolean",
+                Some("array") | Some("list") => "array",
+                Some("object") => "object",
+                _ => "string",
+            };
+            (p.name.clone(), json!({"type": [json_type, "null"]}))
+        })
+        .collect();
+
+    json!({"type": "object", "properties": properties})
+}
+
+// Wraps the extracted slots in the same shape `sentence_to_json` produces,
+// so field matching and the response JSON work unchanged
+pub fn build_extraction_output(endpoint: &Endpoint, extracted: &Value) -> Value {
+    // Some models nest the values under "fields" despite the instructions
+    let values = extracted
+        .get("fields")
+        .and_then(|f| f.as_object())
+        .or_else(|| extracted.as_object());
+
+    let fields: Map<String, Value> = values
+        .map(|obj| {
+            obj.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    json!({
+        "endpoints": [
+            {
+                "id": endpoint.id,
+                "description": endpoint.description,
+                "fields": fields,
+            }
+        ]
+    })
+}
+
+pub async fn extract_parameters(
+    sentence: &str,
+    endpoint: &Endpoint,
+    provider: Arc<dyn ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    info!("Extracting parameters for endpoint: {}", endpoint.id);
+
+    let prompt_manager = PromptManager::new().await?;
+    let parameters = format_parameter_schema(endpoint);
+    let prompt = prompt_manager.format_extract_parameters(
+        sentence,
+        &endpoint.description,
+        &parameters,
+        Some("v1"),
+    );
+    debug!("Parameter extraction prompt:\n{}", prompt);
+
+    let model_config = model_config.with_output(OutputFormat::Schema(output_schema(endpoint)));
+    let response = provider.generate(&prompt, &model_config).await?;
+    debug!("Raw LLM response:\n{}", response);
+
+    let extracted = sanitize_json(&response)?;
+    if !extracted.is_object() {
+        return Err("Invalid JSON structure: expected an object of parameter values".into());
+    }
+
+    Ok(build_extraction_output(endpoint, &extracted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EndpointParameter;
+
+    fn create_test_endpoint() -> Endpoint {
+        Endpoint {
+            id: "send_email".to_string(),
+            text: "send email".to_string(),
+            description: "Send an email".to_string(),
+            parameters: vec![
+                EndpointParameter {
+                    name: "to".to_string(),
+                    description: "Recipient's email address".to_string(),
+                    required: Some(true),
+                    alternatives: None,
+                    semantic_value: None,
+                    param_type: None,
+                    value: None,
+                    default: None,
+                    provenance: None,
+                },
+                EndpointParameter {
+                    name: "attachments".to_string(),
+                    description: "Attachments".to_string(),
+                    required: Some(false),
+                    alternatives: None,
+                    semantic_value: None,
+                    param_type: Some("array".to_string()),
+                    value: None,
+                    default: None,
+                    provenance: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_format_parameter_schema() {
+        let schema = format_parameter_schema(&create_test_endpoint());
+        assert_eq!(
+            schema,
+            "- to (string, required): Recipient's email address\n\
+             - attachments (array, optional): Attachments"
+        );
+    }
+
+    #[test]
+    fn test_output_schema() {
+        assert_eq!(
+            output_schema(&create_test_endpoint()),
+            json!({
+                "type": "object",
+                "properties": {
+                    "to": {"type": ["string", "null"]},
+                    "attachments": {"type": ["array", "null"]},
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_extraction_output() {
+        let endpoint = create_test_endpoint();
+        let extracted = json!({"to": "alice@example.com", "attachments": null});
+        let output = build_extraction_output(&endpoint, &extracted);
+
+        assert_eq!(output["endpoints"][0]["id"], "send_email");
+        assert_eq!(
+            output["endpoints"][0]["fields"],
+            json!({"to": "alice@example.com"})
+        );
+
+        // Values nested under "fields" are unwrapped
+        let nested = json!({"fields": {"to": "bob@example.com"}});
+        let output = build_extraction_output(&endpoint, &nested);
+        assert_eq!(output["endpoints"][0]["fields"]["to"], "bob@example.com");
+    }
+}
@@ -2,26 +2,40 @@ use std::error::Error;
 use std::sync::Arc;
 use tracing::{debug, error, info};
 
-use crate::models::config::load_models_config;
-use crate::models::providers::ModelProvider;
+use crate::models::providers::{ModelConfig, ModelProvider};
 use crate::models::ConfigFile;
 use crate::models::Endpoint;
 use crate::prompts::PromptManager;
 use crate::workflow::extract_matched_action::extract_matched_action;
-use crate::workflow::find_endpoint::find_endpoint_by_substring;
+use crate::workflow::find_endpoint::{find_endpoint_by_substring, match_confidence};
+
+/// Endpoint picked by the model and how unambiguous its answer was
+#[derive(Debug, Clone)]
+pub struct EndpointMatch {
+    pub endpoint: Endpoint,
+    pub confidence: f32,
+}
 
 pub async fn find_closest_endpoint(
     config: &ConfigFile,
     input_sentence: &str,
     provider: Arc<dyn ModelProvider>,
-) -> Result<Endpoint, Box<dyn Error + Send + Sync>> {
+    model_config: &ModelConfig,
+) -> Result<EndpointMatch, Box<dyn Error + Send + Sync>> {
+    let raw_response = ask_closest_endpoint(config, input_sentence, provider, model_config).await?;
+    match_endpoint_answer(config, &raw_response).await
+}
+
+/// Asks the model which endpoint fits the sentence; errors are the provider's
+pub async fn ask_closest_endpoint(
+    config: &ConfigFile,
+    input_sentence: &str,
+    provider: Arc<dyn ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
     info!("Starting endpoint matching for input: {}", input_sentence);
     debug!("Available endpoints: {}", config.endpoints.len());
 
-    // Load model configuration
-    let models_config = load_models_config().await?;
-    let model_config = &models_config.find_endpoint;
-
     // Initialize the PromptManager
     let prompt_manager = PromptManager::new().await?;
 
@@ -41,8 +55,15 @@ pub async fn find_closest_endpoint(
     info!("Using provider with model: {}", model_config.name);
     let raw_response = provider.generate(&prompt, model_config).await?;
     debug!("Raw model response: '{}'", raw_response);
+    Ok(raw_response)
+}
 
-    let cleaned_response = extract_matched_action(&raw_response).await?;
+/// The endpoint named by the model's answer; errors mean the answer is unusable
+pub async fn match_endpoint_answer(
+    config: &ConfigFile,
+    raw_response: &str,
+) -> Result<EndpointMatch, Box<dyn Error + Send + Sync>> {
+    let cleaned_response = extract_matched_action(raw_response).await?;
     info!("Cleaned response: '{}'", cleaned_response);
 
     let matched_endpoint = match find_endpoint_by_substring(config, &cleaned_response) {
@@ -56,6 +77,13 @@ pub async fn find_closest_endpoint(
         }
     };
 
-    info!("Found matching endpoint: {}", matched_endpoint.id);
-    Ok(matched_endpoint)
+    let confidence = match_confidence(config, &cleaned_response);
+    info!(
+        "Found matching endpoint: {} (confidence {:.2})",
+        matched_endpoint.id, confidence
+    );
+    Ok(EndpointMatch {
+        endpoint: matched_endpoint,
+        confidence,
+    })
 }
@@ -2,19 +2,15 @@ use crate::models::{ConfigFile, Endpoint};
 use std::error::Error;
 use tracing::{debug, error};
 
-// Finds the best matching endpoint using substring matching
-pub fn find_endpoint_by_substring<'a>(
-    config: &'a ConfigFile,
-    response: &str,
-) -> Result<&'a Endpoint, Box<dyn Error>> {
+// Endpoints whose text appears in the response, in catalog order
+fn matching_endpoints<'a>(config: &'a ConfigFile, response: &str) -> Vec<&'a Endpoint> {
     let response_lower = response.to_lowercase();
     debug!(
         "Attempting substring matching with response: '{}'",
         response_lower
     );
 
-    // Find all endpoints that might match
-    let matches: Vec<_> = config
+    config
         .endpoints
         .iter()
         .filter(|endpoint| {
@@ -25,7 +21,16 @@ pub fn find_endpoint_by_substring<'a>(
             endpoint_text.split_whitespace().all(|word| response_lower.contains(word))
             // All words in endpoint are in response
         })
-        .collect();
+        .collect()
+}
+
+// Finds the best matching endpoint using substring matching
+pub fn find_endpoint_by_substring<'a>(
+    config: &'a ConfigFile,
+    response: &str,
+) -> Result<&'a Endpoint, Box<dyn Error>> {
+    // Find all endpoints that might match
+    let matches = matching_endpoints(config, response);
 
     debug!("Found {} potential matches", matches.len());
 
@@ -50,6 +55,24 @@ pub fn find_endpoint_by_substring<'a>(
         .copied()
 }
 
+/// How unambiguously the response names a single endpoint: 1.0 when it is exactly
+/// the endpoint text, 0.8 when the text was found in a longer answer, 0.5 when
+/// several endpoints matched and the first one was taken
+pub fn match_confidence(config: &ConfigFile, response: &str) -> f32 {
+    let matches = matching_endpoints(config, response);
+    let answer = response
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase();
+
+    match matches.as_slice() {
+        [] => 0.0,
+        [endpoint] if endpoint.text.trim().to_lowercase() == answer => 1.0,
+        [_] => 0.8,
+        _ => 0.5,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +120,26 @@ mod tests {
             assert!(result.is_err(), "Should not match: {}", case);
         }
     }
+
+    #[test]
+    fn test_match_confidence() {
+        let mut config = create_test_config();
+        assert_eq!(match_confidence(&config, "Schedule meeting."), 1.0);
+        assert_eq!(
+            match_confidence(&config, "The answer is: schedule meeting"),
+            0.8
+        );
+        assert_eq!(match_confidence(&config, "send email"), 0.0);
+
+        config.endpoints.push(Endpoint {
+            id: "cancel_meeting".to_string(),
+            text: "cancel meeting".to_string(),
+            description: "Cancel a meeting".to_string(),
+            parameters: vec![],
+        });
+        assert_eq!(
+            match_confidence(&config, "schedule meeting or cancel meeting"),
+            0.5
+        );
+    }
 }
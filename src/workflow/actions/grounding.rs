@@ -0,0 +1,439 @@
+use crate::utils::text_span::{locate_span, TextSpan};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What to do with values that can't be traced back to the sentence
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GroundingPolicy {
+    /// Keep the value but flag it as unverified
+    #[default]
+    Mark,
+    /// Remove the value from the result
+    Drop,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GroundingConfig {
+    #[serde(default)]
+    pub policy: GroundingPolicy,
+    /// Minimum similarity (0..1) for a fuzzy span match
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+}
+
+fn default_min_similarity() -> f32 {
+    0.85
+}
+
+impl Default for GroundingConfig {
+    fn default() -> Self {
+        Self {
+            policy: GroundingPolicy::default(),
+            min_similarity: default_min_similarity(),
+        }
+    }
+}
+
+/// How a value was found in the sentence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroundingMethod {
+    /// The value appears verbatim, ignoring case
+    Verbatim,
+    /// The value appears once case, accents, punctuation and spacing are normalized
+    Normalized,
+    /// A span of the sentence is close enough to the value
+    Fuzzy,
+    /// The value is a whitelisted transformation of the sentence (number words, relative dates, units)
+    Derived,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grounding {
+    pub method: GroundingMethod,
+    pub span: Option<TextSpan>,
+}
+
+/// Checks that a value is present in, or derivable from, the sentence.
+/// Returns `None` when the value looks invented.
+pub fn ground_value(sentence: &str, value: &Value, config: &GroundingConfig) -> Option<Grounding> {
+    match value {
+        Value::Null => None,
+        // Flags are inferred from phrasing rather than copied from the sentence
+        Value::Bool(_) => Some(Grounding {
+            method: GroundingMethod::Derived,
+            span: None,
+        }),
+        Value::Number(n) => ground_text(sentence, &n.to_string(), config),
+        Value::String(s) => ground_text(sentence, s, config),
+        // Collections are grounded when all of their elements are
+        Value::Array(items) => ground_all(sentence, items.iter(), config),
+        Value::Object(map) => ground_all(sentence, map.values(), config),
+    }
+}
+
+fn ground_all<'a>(
+    sentence: &str,
+    mut values: impl Iterator<Item = &'a Value>,
+    config: &GroundingConfig,
+) -> Option<Grounding> {
+    let mut method = GroundingMethod::Verbatim;
+    let all_grounded = values.all(|v| match ground_value(sentence, v, config) {
+        Some(grounding) => {
+            if grounding.method != GroundingMethod::Verbatim {
+                method = grounding.method;
+            }
+            true
+        }
+        None => false,
+    });
+
+    all_grounded.then_some(Grounding { method, span: None })
+}
+
+fn ground_text(sentence: &str, text: &str, config: &GroundingConfig) -> Option<Grounding> {
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    if let Some(span) = locate_span(sentence, text) {
+        return Some(Grounding {
+            method: GroundingMethod::Verbatim,
+            span: Some(span),
+        });
+    }
+
+    let sentence_tokens = tokenize(sentence);
+    let value_tokens = tokenize(text);
+    if value_tokens.is_empty() {
+        return None;
+    }
+
+    let normalized_sentence = join_tokens(&sentence_tokens);
+    let normalized_value = join_tokens(&value_tokens);
+    if contains_words(&normalized_sentence, &normalized_value) {
+        return Some(Grounding {
+            method: GroundingMethod::Normalized,
+            span: None,
+        });
+    }
+
+    if fuzzy_match(&sentence_tokens, &value_tokens, config.min_similarity) {
+        return Some(Grounding {
+            method: GroundingMethod::Fuzzy,
+            span: None,
+        });
+    }
+
+    // Dates expressed relatively ("tomorrow") and spelled-out numbers
+    if ground_relative_date(&sentence_tokens, text, today_days()) {
+        return Some(Grounding {
+            method: GroundingMethod::Derived,
+            span: None,
+        });
+    }
+
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| ground_number(sentence, n))
+}
+
+fn ground_number(sentence: &str, value: f64) -> Option<Grounding> {
+    let tokens = tokenize(sentence);
+    let numbers = spoken_numbers(&tokens);
+
+    let derived = numbers.iter().any(|(n, next)| {
+        *n == value
+            // Durations given in hours but expected in minutes
+            || (*n * 60.0 == value
+                && next.is_some_and(|unit| unit.starts_with("hour") || unit == "h"))
+    });
+
+    derived.then_some(Grounding {
+        method: GroundingMethod::Derived,
+        span: None,
+    })
+}
+
+// Numbers of the sentence (digits or words), each with the token that follows it
+fn spoken_numbers(tokens: &[String]) -> Vec<(f64, Option<&str>)> {
+    let mut numbers = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].as_str();
+        let parsed = token.parse::<f64>().ok().or_else(|| number_word(token));
+        if let Some(mut n) = parsed {
+            // "twenty five"
+            if n >= 20.0 && n % 10.0 == 0.0 {
+                if let Some(units) = tokens.get(i + 1).and_then(|t| number_word(t)) {
+                    if units < 10.0 {
+                        n += units;
+                        i += 1;
+                    }
+                }
+            }
+            numbers.push((n, tokens.get(i + 1).map(|t| t.as_str())));
+        }
+        i += 1;
+    }
+    numbers
+}
+
+fn number_word(word: &str) -> Option<f64> {
+    const WORDS: [&str; 20] = [
+        "zero",
+        "one",
+        "two",
+        "three",
+        "four",
+        "five",
+        "six",
+        "seven",
+        "eight",
+        "nine",
+        "ten",
+        "eleven",
+        "twelve",
+        "thirteen",
+        "fourteen",
+        "fifteen",
+        "sixteen",
+        "seventeen",
+        "eighteen",
+        "nineteen",
+    ];
+    const TENS: [&str; 8] = [
+        "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+
+    if let Some(n) = WORDS.iter().position(|w| *w == word) {
+        return Some(n as f64);
+    }
+    if let Some(n) = TENS.iter().position(|w| *w == word) {
+        return Some((n as f64 + 2.0) * 10.0);
+    }
+    match word {
+        "hundred" => Some(100.0),
+        "half" => Some(0.5),
+        _ => None,
+    }
+}
+
+fn ground_relative_date(tokens: &[String], text: &str, today: i64) -> bool {
+    let Some(days) = parse_iso_date(text.trim()) else {
+        return false;
+    };
+
+    tokens.iter().any(|token| match token.as_str() {
+        "today" | "tonight" => days == today,
+        "tomorrow" => days == today + 1,
+        "yesterday" => days == today - 1,
+        _ => false,
+    })
+}
+
+fn today_days() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| (d.as_secs() / 86_400) as i64)
+        .unwrap_or_default()
+}
+
+// Days since 1970-01-01 for a `YYYY-MM-DD` date (optionally followed by a time)
+fn parse_iso_date(text: &str) -> Option<i64> {
+    let date = text.get(..10)?;
+    let mut parts = date.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Civil-from-days inverse (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+// Lowercased words with accents folded and punctuation removed
+fn tokenize(text: &str) -> Vec<String> {
+    text.chars()
+        .map(fold_char)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric() && c != '@' && c != '.')
+        .map(|t| t.trim_matches('.'))
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+fn fold_char(c: char) -> char {
+    match c.to_lowercase().next().unwrap_or(c) {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        '’' | '‘' => '\'',
+        other => other,
+    }
+}
+
+fn join_tokens(tokens: &[String]) -> String {
+    tokens.join(" ")
+}
+
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    format!(" {} ", haystack).contains(&format!(" {} ", needle))
+}
+
+// Compares the value with every window of sentence tokens of about the same length
+fn fuzzy_match(sentence: &[String], value: &[String], min_similarity: f32) -> bool {
+    let target = join_tokens(value);
+    let len = value.len();
+
+    (len.saturating_sub(1).max(1)..=len + 1).any(|window| {
+        sentence
+            .windows(window.min(sentence.len()).max(1))
+            .any(|candidate| similarity(&join_tokens(candidate), &target) >= min_similarity)
+    })
+}
+
+fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    // Levenshtein distance with a single row
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+
+    1.0 - row[b.len()] as f32 / longest as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ground(sentence: &str, value: Value) -> Option<GroundingMethod> {
+        ground_value(sentence, &value, &GroundingConfig::default()).map(|g| g.method)
+    }
+
+    #[test]
+    fn test_verbatim_and_normalized_values() {
+        let sentence = "Send an email to alice@example.com about the Q3 report";
+        assert_eq!(
+            ground(sentence, json!("alice@example.com")),
+            Some(GroundingMethod::Verbatim)
+        );
+        assert_eq!(
+            ground(sentence, json!("Q3-report")),
+            Some(GroundingMethod::Normalized)
+        );
+        assert_eq!(
+            ground("Réunion avec Zoé", json!("zoe")),
+            Some(GroundingMethod::Normalized)
+        );
+    }
+
+    #[test]
+    fn test_invented_values_are_not_grounded() {
+        let sentence = "Send an email to Alice about the report";
+        // Invented email domain
+        assert_eq!(ground(sentence, json!("alice@example.com")), None);
+        // Invented ticket title
+        assert_eq!(ground(sentence, json!("Server outage")), None);
+        // Invented customer id
+        assert_eq!(ground(sentence, json!("CUST-00042")), None);
+    }
+
+    #[test]
+    fn test_values_inside_other_words_are_not_verbatim() {
+        assert_eq!(ground("Show the sales of 2012", json!("12")), None);
+        assert_eq!(ground("Show the sales of 2012", json!(12)), None);
+        assert_eq!(ground("Call Diane", json!("an")), None);
+        assert_eq!(
+            ground("Book 12 seats", json!(12)),
+            Some(GroundingMethod::Verbatim)
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_span_matching() {
+        let sentence = "Create a ticket titled databse migraton failed";
+        assert_eq!(
+            ground(sentence, json!("database migration failed")),
+            Some(GroundingMethod::Fuzzy)
+        );
+    }
+
+    #[test]
+    fn test_derived_numbers() {
+        assert_eq!(
+            ground("Schedule a meeting for thirty minutes", json!(30)),
+            Some(GroundingMethod::Derived)
+        );
+        assert_eq!(
+            ground(
+                "Schedule a meeting lasting twenty five minutes",
+                json!("25")
+            ),
+            Some(GroundingMethod::Derived)
+        );
+        assert_eq!(
+            ground("Book a 2 hour meeting", json!(120)),
+            Some(GroundingMethod::Derived)
+        );
+        assert_eq!(
+            ground("Pay 45 euros", json!(45)),
+            Some(GroundingMethod::Verbatim)
+        );
+        assert_eq!(ground("Pay 45 euros", json!(54)), None);
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        let today = parse_iso_date("2026-10-18").unwrap();
+        let tokens = tokenize("Schedule it for tomorrow at 2pm");
+        assert!(ground_relative_date(&tokens, "2026-10-19", today));
+        assert!(!ground_relative_date(&tokens, "2026-10-20", today));
+        assert_eq!(parse_iso_date("1970-01-01"), Some(0));
+        assert_eq!(parse_iso_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_collections() {
+        let sentence = "Invite Alice and Bob";
+        assert!(ground(sentence, json!(["Alice", "Bob"])).is_some());
+        assert!(ground(sentence, json!(["Alice", "Carol"])).is_none());
+        assert!(ground(sentence, json!({"first": "alice"})).is_some());
+        assert!(ground(sentence, json!(true)).is_some());
+        assert!(ground(sentence, json!(null)).is_none());
+    }
+}
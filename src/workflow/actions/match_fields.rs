@@ -1,32 +1,209 @@
-use crate::models::config::load_models_config;
+use crate::json_helper::sanitize_json;
+use crate::models::provenance::{Provenance, ValueSource};
+use crate::models::providers::{ModelConfig, OutputFormat};
 use crate::models::Endpoint;
 use crate::prompts::PromptManager;
-use crate::json_helper::sanitize_json;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::error::Error;
 use tracing::debug;
 
+use crate::ModelProvider;
 use std::sync::Arc;
- use crate::ModelProvider;
-pub async fn match_fields_semantic(
+
+/// Value resolved for one endpoint parameter
+#[derive(Debug, Clone)]
+pub struct MatchedField {
+    pub name: String,
+    pub value: Option<Value>,
+    pub provenance: Option<Provenance>,
+}
+
+/// A field of the generated JSON, addressable by its key or by its path
+/// when nested (e.g. `recipient.email` is reachable as `recipient_email`)
+#[derive(Debug, Clone)]
+struct InputField {
+    key: String,
+    root: String,
+    value: Value,
+}
+
+// Collects the fields of every entry of the generated `endpoints` array.
+// Top-level keys come first so they win over nested paths and leaf names.
+fn collect_input_fields(
     input_json: &Value,
-    endpoint: &Endpoint,
-    provider: Arc<dyn ModelProvider>,  // Add this parameter
-) -> Result<Vec<(String, String, Option<String>)>, Box<dyn Error + Send + Sync>> {
-    let input_fields = input_json
+) -> Result<Vec<InputField>, Box<dyn Error + Send + Sync>> {
+    let entries = input_json
         .get("endpoints")
         .ok_or("Invalid JSON structure")?
         .as_array()
-        .and_then(|arr| arr.first())
-        .ok_or("No endpoints found in JSON")?
-        .get("fields")
-        .ok_or("No fields found in JSON")?
-        .as_object()
-        .ok_or("Fields is not an object")?
+        .ok_or("No endpoints found in JSON")?;
+
+    let objects: Vec<&Map<String, Value>> = entries
         .iter()
-        .map(|(k, v)| format!("{}: {}", k, v))
-        .collect::<Vec<_>>()
-        .join(", ");
+        .filter_map(|entry| entry.get("fields").and_then(|f| f.as_object()))
+        .collect();
+
+    if objects.is_empty() {
+        return Err("No fields found in JSON".into());
+    }
+
+    let mut fields = Vec::new();
+    for object in &objects {
+        for (key, value) in object.iter() {
+            fields.push(InputField {
+                key: key.clone(),
+                root: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    let mut nested = Vec::new();
+    let mut leaves = Vec::new();
+    for object in &objects {
+        for (key, value) in object.iter() {
+            collect_nested(key, key, value, &mut nested, &mut leaves);
+        }
+    }
+    fields.extend(nested);
+    fields.extend(leaves);
+
+    Ok(fields)
+}
+
+fn collect_nested(
+    root: &str,
+    path: &str,
+    value: &Value,
+    nested: &mut Vec<InputField>,
+    leaves: &mut Vec<InputField>,
+) {
+    if let Value::Object(object) = value {
+        for (key, child) in object {
+            let child_path = format!("{}_{}", path, key);
+            nested.push(InputField {
+                key: child_path.clone(),
+                root: root.to_string(),
+                value: child.clone(),
+            });
+            leaves.push(InputField {
+                key: key.clone(),
+                root: root.to_string(),
+                value: child.clone(),
+            });
+            collect_nested(root, &child_path, child, nested, leaves);
+        }
+    }
+}
+
+fn find_field<'a>(fields: &'a [InputField], name: &str) -> Option<&'a InputField> {
+    let dotted = name.replace('.', "_");
+    fields.iter().find(|f| f.key == name || f.key == dotted)
+}
+
+/// Renders a JSON value for `semantic_value`: strings as-is, anything else as JSON
+pub fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `direct_source` labels values whose field name is exactly the parameter name:
+/// `Exact` for free-form JSON, `Extractor` when the JSON came from schema-guided extraction.
+pub async fn match_fields_semantic(
+    sentence: &str,
+    input_json: &Value,
+    endpoint: &Endpoint,
+    direct_source: ValueSource,
+    provider: Arc<dyn ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<Vec<MatchedField>, Box<dyn Error + Send + Sync>> {
+    let input_fields = collect_input_fields(input_json)?;
+
+    // Resolve what we can without the model: exact names first, then alternatives
+    let mut used_roots: Vec<&str> = Vec::new();
+    let mut values: Vec<Option<(Value, Provenance)>> = Vec::new();
+    for param in &endpoint.parameters {
+        let mut field = find_field(&input_fields, &param.name);
+
+        if field.is_none() {
+            if let Some(alternatives) = &param.alternatives {
+                field = alternatives
+                    .iter()
+                    .find_map(|alt| find_field(&input_fields, alt));
+            }
+        }
+
+        values.push(field.map(|f| {
+            used_roots.push(f.root.as_str());
+            let (source, field_name) = if f.key == param.name {
+                (direct_source, None)
+            } else {
+                (ValueSource::Alias, Some(f.key.clone()))
+            };
+            let provenance = Provenance::new(source, field_name, &f.value, sentence);
+            (f.value.clone(), provenance)
+        }));
+    }
+
+    // The semantic stage can only map leftover input fields onto unresolved
+    // parameters, so skip the model call when either side is empty
+    let has_unresolved = values.iter().any(|v| v.is_none());
+    let leftover: Map<String, Value> = input_fields
+        .iter()
+        .filter(|f| f.key == f.root && !used_roots.contains(&f.root.as_str()))
+        .map(|f| (f.key.clone(), f.value.clone()))
+        .collect();
+
+    let json_response = if has_unresolved && !leftover.is_empty() {
+        semantic_mapping(&leftover, endpoint, provider, model_config).await?
+    } else {
+        debug!("All fields resolved by name or alternatives, skipping semantic matching");
+        Value::Null
+    };
+
+    let mut matched_fields = Vec::new();
+    for (param, mut resolved) in endpoint.parameters.iter().zip(values) {
+        // If still no match, check semantic matching result
+        if resolved.is_none() {
+            resolved = json_response
+                .get(&param.name)
+                .filter(|v| !v.is_null())
+                .map(|v| {
+                    let provenance = Provenance::new(ValueSource::Semantic, None, v, sentence);
+                    (v.clone(), provenance)
+                });
+        }
+
+        // Finally fall back to the catalog default
+        if resolved.is_none() {
+            resolved = param.default.as_ref().map(|v| {
+                let provenance = Provenance::new(ValueSource::Default, None, v, sentence);
+                (v.clone(), provenance)
+            });
+        }
+
+        let (value, provenance) = resolved.unzip();
+        matched_fields.push(MatchedField {
+            name: param.name.clone(),
+            value,
+            provenance,
+        });
+    }
+
+    Ok(matched_fields)
+}
+
+// Asks the model to map input field names onto the endpoint parameters
+async fn semantic_mapping(
+    input_fields: &Map<String, Value>,
+    endpoint: &Endpoint,
+    provider: Arc<dyn ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    // Serialized as JSON so nested objects and arrays keep their structure
+    let input_fields = serde_json::to_string(input_fields)?;
 
     let parameters = endpoint
         .parameters
@@ -47,51 +224,63 @@ pub async fn match_fields_semantic(
         .replace("{parameters}", &parameters);
 
     debug!("Field matching prompt:\n{}", prompt);
-    debug!("Calling Ollama for field matching");
-    // Load model configuration
-    let models_config = load_models_config().await?;
-    let model_config = &models_config.sentence_to_json;
-
-    // let response = call_ollama_with_config(model_config, &prompt).await?;
-
-    let response = provider.generate(&prompt, model_config).await?;
+    debug!("Calling provider for field matching");
+    let model_config = model_config.with_output(OutputFormat::Json);
+    let response = provider.generate(&prompt, &model_config).await?;
 
     let json_response = sanitize_json(&response)?;
 
     debug!("Semantic matching response: {:?}", json_response);
+    Ok(json_response)
+}
 
-    let mut matched_fields = Vec::new();
-    let input_fields = input_json["endpoints"][0]["fields"]
-        .as_object()
-        .ok_or("Invalid JSON structure")?;
-
-    for param in &endpoint.parameters {
-        // First try exact match
-        let mut value = input_fields.get(&param.name).map(|v| v.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
 
-        // If no exact match, try alternatives
-        if value.is_none() {
-            if let Some(alternatives) = &param.alternatives {
-                for alt in alternatives {
-                    // Changed this line
-                    if let Some(v) = input_fields.get(alt) {
-                        // Now alt is a &String
-                        value = Some(v.to_string());
-                        break;
-                    }
-                }
-            }
-        }
+    #[test]
+    fn test_collect_fields_from_all_entries_and_nested_objects() {
+        let input = json!({
+            "endpoints": [
+                {"fields": {"to": "alice@example.com", "recipient": {"name": "Alice"}}},
+                {"fields": {"attachments": ["a.pdf", "b.pdf"], "to": "ignored@example.com"}}
+            ]
+        });
+        let fields = collect_input_fields(&input).unwrap();
 
-        // If still no match, check semantic matching result
-        if value.is_none() {
-            if let Some(v) = json_response.get(&param.name) {
-                value = Some(v.to_string().trim_matches('"').to_string());
-            }
-        }
+        // First entry wins for duplicated keys
+        assert_eq!(
+            find_field(&fields, "to").unwrap().value,
+            "alice@example.com"
+        );
+        // Arrays from later entries keep their structure
+        assert_eq!(
+            find_field(&fields, "attachments").unwrap().value,
+            json!(["a.pdf", "b.pdf"])
+        );
+        // Nested values are reachable by path and by leaf name
+        let by_path = find_field(&fields, "recipient_name").unwrap();
+        assert_eq!(by_path.value, "Alice");
+        assert_eq!(by_path.root, "recipient");
+        assert_eq!(
+            find_field(&fields, "recipient.name").unwrap().value,
+            "Alice"
+        );
+        assert_eq!(find_field(&fields, "name").unwrap().value, "Alice");
+    }
 
-        matched_fields.push((param.name.clone(), param.description.clone(), value));
+    #[test]
+    fn test_collect_fields_without_fields() {
+        assert!(collect_input_fields(&json!({"endpoints": [{}]})).is_err());
+        assert!(collect_input_fields(&json!({})).is_err());
     }
 
-    Ok(matched_fields)
+    #[test]
+    fn test_value_to_text() {
+        assert_eq!(value_to_text(&json!("Alice")), "Alice");
+        assert_eq!(value_to_text(&json!(30)), "30");
+        assert_eq!(value_to_text(&json!(true)), "true");
+        assert_eq!(value_to_text(&json!(["a", "b"])), r#"["a","b"]"#);
+    }
 }
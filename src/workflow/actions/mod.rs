@@ -1,5 +1,8 @@
 pub mod extract_matched_action;
+pub mod extract_parameters;
 pub mod find_closest_endpoint;
 pub mod find_endpoint;
+pub mod grounding;
 pub mod match_fields;
+pub mod select_endpoint_tool;
 pub mod sentence_to_json;
@@ -0,0 +1,308 @@
+use super::extract_parameters::{build_extraction_output, output_schema};
+use crate::models::providers::tools::{tool_name, ToolDefinition};
+use crate::models::providers::{ModelConfig, ModelProvider};
+use crate::models::{ConfigFile, Endpoint};
+use crate::prompts::PromptManager;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::{error::Error, sync::Arc};
+use tracing::{debug, info, warn};
+
+/// Endpoint chosen by the model and the parameter values it passed
+#[derive(Debug, Clone)]
+pub struct ToolSelection {
+    pub endpoint: Endpoint,
+    /// Arguments in the shape `sentence_to_json` produces
+    pub json_output: Value,
+}
+
+// One tool per endpoint, its parameters described by the extraction schema
+fn endpoint_tool(endpoint: &Endpoint, name: String) -> ToolDefinition {
+    let mut parameters = output_schema(endpoint);
+    for param in &endpoint.parameters {
+        if let Some(property) = parameters["properties"].get_mut(&param.name) {
+            property["description"] = Value::String(param.description.clone());
+        }
+    }
+
+    let description = if endpoint.description.trim().is_empty() {
+        endpoint.text.clone()
+    } else {
+        endpoint.description.clone()
+    };
+
+    ToolDefinition {
+        name,
+        description,
+        parameters,
+    }
+}
+
+// Sanitised ids can collide ("send.email" and "send_email"), later endpoints
+// get an index suffix that keeps the name within 64 characters
+fn unique_tool_name(id: &str, taken: &HashMap<String, &Endpoint>) -> String {
+    let name = tool_name(id);
+    if !taken.contains_key(&name) {
+        return name;
+    }
+    (2..)
+        .map(|index| {
+            let suffix = format!("_{}", index);
+            let keep = name.len().min(64 - suffix.len());
+            format!("{}{}", &name[..keep], suffix)
+        })
+        .find(|candidate| !taken.contains_key(candidate))
+        .expect("an unused suffix")
+}
+
+/// The tools offered for the catalog and the endpoint each tool name stands for
+pub fn endpoint_tools(config: &ConfigFile) -> (Vec<ToolDefinition>, HashMap<String, &Endpoint>) {
+    let mut tools = Vec::with_capacity(config.endpoints.len());
+    let mut endpoints = HashMap::new();
+    for endpoint in &config.endpoints {
+        let name = unique_tool_name(&endpoint.id, &endpoints);
+        tools.push(endpoint_tool(endpoint, name.clone()));
+        endpoints.insert(name, endpoint);
+    }
+    (tools, endpoints)
+}
+
+/// Offers every endpoint of the catalog as a tool and returns the one the
+/// model called. `None` when the model didn't call a known tool; providers
+/// without tool support fail with `ProviderError::ToolsUnsupported`.
+pub async fn select_endpoint_tool(
+    config: &ConfigFile,
+    sentence: &str,
+    provider: Arc<dyn ModelProvider>,
+    model_config: &ModelConfig,
+) -> Result<Option<ToolSelection>, Box<dyn Error + Send + Sync>> {
+    info!("Selecting endpoint through tool calling for: {}", sentence);
+
+    let prompt_manager = PromptManager::new().await?;
+    let prompt = prompt_manager.format_select_endpoint_tool(sentence, Some("v1"));
+    let (tools, endpoints) = endpoint_tools(config);
+    debug!("Offering {} endpoint tools", tools.len());
+
+    let Some(call) = provider.call_tool(&prompt, model_config, &tools).await? else {
+        warn!("The model answered without calling a tool");
+        return Ok(None);
+    };
+    debug!("Tool call: {} {}", call.name, call.arguments);
+
+    let Some(&endpoint) = endpoints.get(&call.name) else {
+        warn!("The model called an unknown tool: {}", call.name);
+        return Ok(None);
+    };
+
+    Ok(Some(ToolSelection {
+        endpoint: endpoint.clone(),
+        json_output: build_extraction_output(endpoint, &call.arguments),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::error::ProviderError;
+    use crate::models::providers::failover::FailoverProvider;
+    use crate::models::providers::tools::ToolCall;
+    use crate::models::EndpointParameter;
+    use crate::workflow::trace::{ExecutionTrace, TraceEvent};
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    // Calls the configured tool, whatever the prompt
+    struct CallingProvider(Option<ToolCall>);
+
+    #[async_trait]
+    impl ModelProvider for CallingProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            Err("not expected to generate".into())
+        }
+
+        async fn call_tool(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+            tools: &[ToolDefinition],
+        ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+            assert_eq!(tools.len(), 2);
+            Ok(self.0.clone())
+        }
+
+        fn model_name(&self, _model: &ModelConfig) -> String {
+            "stub".to_string()
+        }
+    }
+
+    // Answers every call with a 529
+    struct OverloadedProvider;
+
+    #[async_trait]
+    impl ModelProvider for OverloadedProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+        ) -> Result<String, Box<dyn Error + Send + Sync>> {
+            Err("not expected to generate".into())
+        }
+
+        async fn call_tool(
+            &self,
+            _prompt: &str,
+            _model: &ModelConfig,
+            _tools: &[ToolDefinition],
+        ) -> Result<Option<ToolCall>, Box<dyn Error + Send + Sync>> {
+            Err(ProviderError::Http {
+                provider: "Claude".to_string(),
+                status: 529,
+                body: String::new(),
+                retry_after: None,
+            }
+            .into())
+        }
+    }
+
+    fn catalog() -> ConfigFile {
+        let parameter = |name: &str, description: &str| EndpointParameter {
+            name: name.to_string(),
+            description: description.to_string(),
+            required: Some(true),
+            alternatives: None,
+            semantic_value: None,
+            param_type: None,
+            value: None,
+            default: None,
+            provenance: None,
+        };
+        ConfigFile {
+            endpoints: vec![
+                Endpoint {
+                    id: "send.email".to_string(),
+                    text: "send email".to_string(),
+                    description: "Send an email".to_string(),
+                    parameters: vec![parameter("to", "Recipient's email address")],
+                },
+                Endpoint {
+                    id: "create_ticket".to_string(),
+                    text: "create a support ticket".to_string(),
+                    description: String::new(),
+                    parameters: vec![parameter("title", "Ticket title")],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_endpoint_tools() {
+        let catalog = catalog();
+        let (tools, _) = endpoint_tools(&catalog);
+        assert_eq!(tools[0].name, "send_email");
+        assert_eq!(tools[0].description, "Send an email");
+        assert_eq!(
+            tools[0].parameters["properties"]["to"],
+            json!({"type": ["string", "null"], "description": "Recipient's email address"})
+        );
+        // Endpoints without a description are described by their text
+        assert_eq!(tools[1].description, "create a support ticket");
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_is_mapped_back_to_its_endpoint() {
+        let provider = Arc::new(CallingProvider(Some(ToolCall {
+            name: "send_email".to_string(),
+            arguments: json!({"to": "alice@example.com"}),
+        })));
+        let selection = select_endpoint_tool(
+            &catalog(),
+            "email alice@example.com",
+            provider,
+            &ModelConfig::default(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(selection.endpoint.id, "send.email");
+        assert_eq!(
+            selection.json_output["endpoints"][0]["fields"],
+            json!({"to": "alice@example.com"})
+        );
+
+        let unknown = Arc::new(CallingProvider(Some(ToolCall {
+            name: "delete_everything".to_string(),
+            arguments: json!({}),
+        })));
+        let selection = select_endpoint_tool(&catalog(), "oops", unknown, &ModelConfig::default())
+            .await
+            .unwrap();
+        assert!(selection.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_colliding_tool_names_are_made_unique() {
+        let mut catalog = catalog();
+        let mut colliding = catalog.endpoints[0].clone();
+        colliding.id = "send_email".to_string();
+        catalog.endpoints[1] = colliding;
+
+        let (tools, endpoints) = endpoint_tools(&catalog);
+        assert_eq!(tools[0].name, "send_email");
+        assert_eq!(tools[1].name, "send_email_2");
+        assert_eq!(endpoints["send_email_2"].id, "send_email");
+
+        let provider = Arc::new(CallingProvider(Some(ToolCall {
+            name: "send_email_2".to_string(),
+            arguments: json!({"to": "bob@example.com"}),
+        })));
+        let selection = select_endpoint_tool(
+            &catalog,
+            "email bob@example.com",
+            provider,
+            &ModelConfig::default(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(selection.endpoint.id, "send_email");
+
+        // The suffix fits within the 64 characters of a name
+        let taken = HashMap::from([("a".repeat(64), &catalog.endpoints[0])]);
+        let name = unique_tool_name(&"a".repeat(80), &taken);
+        assert_eq!(name.len(), 64);
+        assert!(name.ends_with("a_2"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_through_a_failover_chain() {
+        let calling: Arc<dyn ModelProvider> = Arc::new(CallingProvider(Some(ToolCall {
+            name: "create_ticket".to_string(),
+            arguments: json!({"title": "Printer is down"}),
+        })));
+        let chain = Arc::new(FailoverProvider::new(vec![
+            ("claude".to_string(), Arc::new(OverloadedProvider)),
+            ("ollama".to_string(), calling),
+        ]));
+
+        let trace = ExecutionTrace::default();
+        let selection = trace
+            .scope(select_endpoint_tool(
+                &catalog(),
+                "the printer is down",
+                chain,
+                &ModelConfig::default(),
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(selection.endpoint.id, "create_ticket");
+        assert!(matches!(
+            &trace.entries()[0].event,
+            TraceEvent::ProviderFailed { provider, .. } if provider == "claude"
+        ));
+    }
+}
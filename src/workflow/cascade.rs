@@ -0,0 +1,196 @@
+// src/workflow/cascade.rs - Cheap-first model cascade: escalate to a stronger model when the
+// cheap one's answer doesn't hold up
+
+use super::actions::grounding::{ground_value, GroundingConfig};
+use super::config::StepRouting;
+use crate::models::EndpointParameter;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+fn default_min_confidence() -> f32 {
+    0.6
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Stronger model a step escalates to, and when
+#[derive(Debug, Deserialize, Clone)]
+pub struct CascadeConfig {
+    /// Entry of the `models` section to escalate to, defaults to the step's model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Provider to escalate to, defaults to the step's provider
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Escalate when the cheap answer's confidence is below this threshold
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f32,
+    /// Escalate when a value can't be traced back to the sentence (field matching)
+    #[serde(default = "default_true")]
+    pub escalate_on_ungrounded: bool,
+}
+
+/// Routing of a step's stronger model together with its escalation thresholds
+#[derive(Debug, Clone)]
+pub struct Cascade {
+    pub routing: StepRouting,
+    pub config: CascadeConfig,
+}
+
+/// Why a step escalated to its stronger model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationReason {
+    /// The answer's confidence was below `min_confidence`
+    LowConfidence,
+    /// The answer couldn't be parsed, matched no endpoint, missed a required
+    /// parameter or had a value of the wrong type
+    SchemaValidation,
+    /// A value wasn't found in the sentence
+    Grounding,
+}
+
+// Whether a value fits the declared parameter type; unknown types accept anything
+fn value_matches_type(value: &Value, param_type: &str) -> bool {
+    match param_type.to_lowercase().as_str() {
+        "string" => value.is_string(),
+        "number" | "float" => {
+            value.is_number()
+                || value
+                    .as_str()
+                    .is_some_and(|s| s.trim().parse::<f64>().is_ok())
+        }
+        "integer" | "int" => {
+            value.is_i64()
+                || value.is_u64()
+                || value
+                    .as_str()
+                    .is_some_and(|s| s.trim().parse::<i64>().is_ok())
+        }
+        "boolean" | "bool" => {
+            value.is_boolean() || matches!(value.as_str(), Some("true") | Some("false"))
+        }
+        "array" | "list" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+/// Checks the parameters resolved by the cheap model, returning why they
+/// should be resolved again by the stronger one
+pub fn assess_parameters(
+    sentence: &str,
+    parameters: &[EndpointParameter],
+    config: &CascadeConfig,
+) -> Option<EscalationReason> {
+    for param in parameters {
+        match &param.value {
+            None if param.required.unwrap_or(false) => {
+                return Some(EscalationReason::SchemaValidation)
+            }
+            Some(value) => {
+                if let Some(param_type) = &param.param_type {
+                    if !value_matches_type(value, param_type) {
+                        return Some(EscalationReason::SchemaValidation);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Catalog defaults are not the model's answer and are not held against it
+    let model_values = parameters.iter().filter_map(|param| {
+        let provenance = param.provenance.as_ref()?;
+        let value = param.value.as_ref()?;
+        provenance.needs_grounding().then_some((value, provenance))
+    });
+
+    for (value, provenance) in model_values {
+        if config.escalate_on_ungrounded
+            && ground_value(sentence, value, &GroundingConfig::default()).is_none()
+        {
+            return Some(EscalationReason::Grounding);
+        }
+        if provenance.confidence < config.min_confidence {
+            return Some(EscalationReason::LowConfidence);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::provenance::{Provenance, ValueSource};
+    use serde_json::json;
+
+    fn cascade_config() -> CascadeConfig {
+        serde_yaml::from_str("model: strong").unwrap()
+    }
+
+    fn parameter(name: &str, param_type: &str, value: Option<Value>) -> EndpointParameter {
+        EndpointParameter {
+            name: name.to_string(),
+            description: String::new(),
+            required: Some(true),
+            alternatives: None,
+            semantic_value: None,
+            param_type: Some(param_type.to_string()),
+            value,
+            default: None,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn test_assess_parameters() {
+        let sentence = "Send the report to alice@example.com in 3 days";
+        let config = cascade_config();
+        assert_eq!(config.min_confidence, 0.6);
+
+        let mut to = parameter("to", "string", Some(json!("alice@example.com")));
+        to.provenance = Some(Provenance::new(
+            ValueSource::Exact,
+            None,
+            &json!("alice@example.com"),
+            sentence,
+        ));
+        let days = parameter("days", "integer", Some(json!(3)));
+        assert_eq!(
+            assess_parameters(sentence, &[to.clone(), days.clone()], &config),
+            None
+        );
+
+        // Missing required value or wrong type
+        let missing = parameter("subject", "string", None);
+        assert_eq!(
+            assess_parameters(sentence, &[to.clone(), missing], &config),
+            Some(EscalationReason::SchemaValidation)
+        );
+        let wrong_type = parameter("days", "integer", Some(json!("soon")));
+        assert_eq!(
+            assess_parameters(sentence, &[wrong_type], &config),
+            Some(EscalationReason::SchemaValidation)
+        );
+
+        // Invented value
+        let mut invented = to.clone();
+        invented.value = Some(json!("bob@example.com"));
+        assert_eq!(
+            assess_parameters(sentence, &[invented], &config),
+            Some(EscalationReason::Grounding)
+        );
+
+        // Grounded but uncertain semantic mapping
+        let mut uncertain = to;
+        uncertain.provenance.as_mut().unwrap().confidence = 0.4;
+        assert_eq!(
+            assess_parameters(sentence, &[uncertain], &config),
+            Some(EscalationReason::LowConfidence)
+        );
+    }
+}
@@ -1,5 +1,33 @@
+use super::actions::grounding::GroundingConfig;
+use super::cascade::{Cascade, CascadeConfig};
+use crate::models::providers::hedged::HedgeConfig;
+use crate::models::providers::truncation::TruncationConfig;
 use serde::Deserialize;
 
+/// Order in which the endpoint and its parameters are resolved
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowMode {
+    /// Extract free-form JSON from the sentence, then match it to an endpoint
+    #[default]
+    SentenceFirst,
+    /// Match the endpoint first, then extract exactly its declared parameters
+    EndpointFirst,
+    /// Let the model call the endpoint as a tool, which yields the endpoint and
+    /// its parameters at once
+    ToolCalling,
+}
+
+impl WorkflowMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkflowMode::SentenceFirst => "sentence_first",
+            WorkflowMode::EndpointFirst => "endpoint_first",
+            WorkflowMode::ToolCalling => "tool_calling",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct WorkflowConfig {
     pub steps: Vec<StepConfig>,
@@ -10,6 +38,109 @@ pub struct StepConfig {
     pub name: String,
     pub enabled: bool,
     pub retry: Option<RetryConfig>,
+    /// Longest wait for one attempt of the catalog fetch (configuration loading)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Name of the entry in the `models` section used by this step
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Name of the provider used by this step, defaults to the selected provider
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Settings of the grounding check step
+    #[serde(default)]
+    pub grounding: Option<GroundingConfig>,
+    /// Stronger model to escalate to (endpoint and field matching)
+    #[serde(default)]
+    pub cascade: Option<CascadeConfig>,
+    /// Duplicate slow model calls of this step
+    #[serde(default)]
+    pub hedge: Option<HedgeConfig>,
+    /// Handling of responses cut at `max_tokens`, retried with a larger budget by default
+    #[serde(default)]
+    pub truncation: Option<TruncationConfig>,
+}
+
+impl StepConfig {
+    /// Applies the per-step overrides from config.yaml
+    pub fn apply_overrides(&mut self, overrides: &StepOverrides) {
+        if overrides.model.is_some() {
+            self.model = overrides.model.clone();
+        }
+        if overrides.provider.is_some() {
+            self.provider = overrides.provider.clone();
+        }
+        if let Some(enabled) = overrides.enabled {
+            self.enabled = enabled;
+        }
+        if overrides.grounding.is_some() {
+            self.grounding = overrides.grounding.clone();
+        }
+        if overrides.cascade.is_some() {
+            self.cascade = overrides.cascade.clone();
+        }
+        if overrides.hedge.is_some() {
+            self.hedge = overrides.hedge.clone();
+        }
+        if overrides.truncation.is_some() {
+            self.truncation = overrides.truncation.clone();
+        }
+    }
+
+    pub fn routing(&self, default_model: &str) -> StepRouting {
+        StepRouting {
+            model: self
+                .model
+                .clone()
+                .unwrap_or_else(|| default_model.to_string()),
+            provider: self.provider.clone(),
+            hedge: self.hedge.clone(),
+            truncation: self.truncation.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Routing of the stronger model when the step runs as a cascade
+    pub fn cascade(&self, default_model: &str) -> Option<Cascade> {
+        let config = self.cascade.clone()?;
+        let routing = self.routing(default_model);
+        Some(Cascade {
+            routing: StepRouting {
+                model: config.model.clone().unwrap_or(routing.model),
+                provider: config.provider.clone().or(routing.provider),
+                hedge: routing.hedge,
+                truncation: routing.truncation,
+            },
+            config,
+        })
+    }
+}
+
+/// Per-step settings that can be overridden from the `workflow.steps` section of config.yaml
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct StepOverrides {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub grounding: Option<GroundingConfig>,
+    #[serde(default)]
+    pub cascade: Option<CascadeConfig>,
+    #[serde(default)]
+    pub hedge: Option<HedgeConfig>,
+    #[serde(default)]
+    pub truncation: Option<TruncationConfig>,
+}
+
+/// Model configuration and provider a step sends its prompts to
+#[derive(Debug, Clone)]
+pub struct StepRouting {
+    pub model: String,
+    pub provider: Option<String>,
+    pub hedge: Option<HedgeConfig>,
+    pub truncation: TruncationConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -1,7 +1,14 @@
 use crate::models::{
-    providers::ModelProvider, ConfigFile, Endpoint, EndpointParameter, ModelsConfig,
+    providers::{
+        hedged::HedgedProvider, truncation::TruncationGuard, ModelConfig, ModelProvider,
+        ProviderRegistry,
+    },
+    ConfigFile, Endpoint, EndpointParameter, ModelsConfig,
 };
+use crate::workflow::trace::ExecutionTrace;
+use crate::workflow::StepRouting;
 use serde_json::Value;
+use std::error::Error;
 use std::sync::Arc;
 
 // Remove the Debug derive since dyn ModelProvider doesn't implement Debug
@@ -15,27 +22,72 @@ pub struct WorkflowContext {
     pub endpoints_config: Option<ConfigFile>,
     // Processing state
     pub json_output: Option<Value>,
+    /// Whether `json_output` was produced by schema-guided parameter extraction
+    pub schema_extraction: bool,
     pub matched_endpoint: Option<Endpoint>,
     pub parameters: Vec<EndpointParameter>,
     pub endpoint_id: Option<String>,
     pub endpoint_description: Option<String>,
-    pub provider: Arc<dyn ModelProvider>,
+    pub providers: Arc<ProviderRegistry>,
+    pub trace: ExecutionTrace,
 }
 
 impl WorkflowContext {
-    pub fn new(sentence: String, provider: Arc<dyn ModelProvider>) -> Self {
+    pub fn new(sentence: String, providers: Arc<ProviderRegistry>) -> Self {
         Self {
             sentence,
             email: None,
-            provider,
+            providers,
             models_config: None,
             endpoints_config: None,
             json_output: None,
+            schema_extraction: false,
             matched_endpoint: None,
             parameters: vec![],
             endpoint_id: None,
             endpoint_description: None,
+            trace: ExecutionTrace::default(),
+        }
+    }
+
+    /// Resolves the provider a step is routed to, hedged when the step asks for
+    /// it and recovering truncated responses as the step is configured to
+    pub fn provider_for(
+        &self,
+        routing: &StepRouting,
+    ) -> Result<Arc<dyn ModelProvider>, Box<dyn Error + Send + Sync>> {
+        let mut provider = self.providers.resolve(routing.provider.as_deref())?;
+        if let Some(hedge) = &routing.hedge {
+            let secondary = match &hedge.provider {
+                Some(name) => self.providers.resolve(Some(name))?,
+                None => provider.clone(),
+            };
+            let provider_name = self.providers.resolved_name(routing.provider.as_deref());
+            let latencies = self.providers.latency_window(provider_name, &routing.model);
+            provider = Arc::new(HedgedProvider::new(
+                provider,
+                secondary,
+                latencies,
+                hedge.clone(),
+            ));
         }
+
+        Ok(Arc::new(TruncationGuard::new(
+            provider,
+            routing.truncation.clone(),
+        )))
+    }
+
+    /// Resolves the model configuration a step is routed to
+    pub fn model_config_for(
+        &self,
+        routing: &StepRouting,
+    ) -> Result<ModelConfig, Box<dyn Error + Send + Sync>> {
+        let models_config = self
+            .models_config
+            .as_ref()
+            .ok_or("Models configuration not loaded")?;
+        Ok(models_config.get(&routing.model)?.clone())
     }
 }
 
@@ -48,11 +100,13 @@ impl std::fmt::Debug for WorkflowContext {
             .field("models_config", &self.models_config)
             .field("endpoints_config", &self.endpoints_config)
             .field("json_output", &self.json_output)
+            .field("schema_extraction", &self.schema_extraction)
             .field("matched_endpoint", &self.matched_endpoint)
             .field("parameters", &self.parameters)
             .field("endpoint_id", &self.endpoint_id)
             .field("endpoint_description", &self.endpoint_description)
-            .field("provider", &"<dyn ModelProvider>")
+            .field("providers", &self.providers)
+            .field("trace", &self.trace)
             .finish()
     }
 }
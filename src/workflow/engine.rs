@@ -1,6 +1,5 @@
-use crate::models::providers::ModelProvider;
-
 use super::config::RetryConfig;
+use super::trace::TraceEvent;
 use super::{config::StepConfig, steps::WorkflowStep, WorkflowContext};
 use std::error::Error;
 use std::sync::Arc;
@@ -20,10 +19,9 @@ impl WorkflowEngine {
 
     pub async fn execute(
         &self,
-        sentence: String,
-        provider: Arc<dyn ModelProvider>,
+        mut context: WorkflowContext,
     ) -> Result<WorkflowContext, Box<dyn Error + Send + Sync>> {
-        let mut context = WorkflowContext::new(sentence, provider);
+        let trace = context.trace.clone();
 
         for (config, step) in &self.steps {
             if !config.enabled {
@@ -31,19 +29,35 @@ impl WorkflowEngine {
             }
 
             tracing::info!("Executing step: {}", step.name());
+            trace.enter_step(Some(step.name()));
+            let started = std::time::Instant::now();
 
-            let result = match &config.retry {
-                Some(retry) => {
-                    self.execute_with_retry(step.as_ref(), &mut context, retry)
-                        .await
-                }
-                None => step.execute(&mut context).await,
-            };
+            // Providers called by the step record into the trace of this run
+            let result = trace
+                .scope(async {
+                    match &config.retry {
+                        Some(retry) => {
+                            self.execute_with_retry(step.as_ref(), &mut context, retry)
+                                .await
+                        }
+                        None => step.execute(&mut context).await,
+                    }
+                })
+                .await;
 
-            if let Err(e) = result {
-                tracing::error!("Step {} failed: {}", step.name(), e);
-                return Err(e);
+            let duration_ms = started.elapsed().as_millis() as u64;
+            match result {
+                Ok(()) => trace.push(TraceEvent::StepCompleted { duration_ms }),
+                Err(e) => {
+                    tracing::error!("Step {} failed: {}", step.name(), e);
+                    trace.push(TraceEvent::StepFailed {
+                        duration_ms,
+                        error: e.to_string(),
+                    });
+                    return Err(e);
+                }
             }
+            trace.enter_step(None);
         }
 
         Ok(context)
@@ -1,11 +1,16 @@
 mod actions;
+pub mod cascade;
 mod config;
 pub mod context;
 mod engine;
+pub mod progress;
 mod steps;
+pub mod trace;
 
 pub use actions::*;
-pub use config::WorkflowConfig;
+pub use config::{
+    RetryConfig, StepConfig, StepOverrides, StepRouting, WorkflowConfig, WorkflowMode,
+};
 pub use context::WorkflowContext;
 pub use engine::WorkflowEngine;
 pub use steps::WorkflowStep;
@@ -0,0 +1,82 @@
+// src/workflow/progress.rs - Token-level progress of model calls, forwarded to streaming clients
+
+use super::trace;
+use std::future::Future;
+use tokio::sync::mpsc::UnboundedSender;
+
+tokio::task_local! {
+    static CURRENT_SINK: ProgressSink;
+}
+
+/// A chunk of text produced by a model call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Step that made the call, `None` outside of steps
+    pub step: Option<String>,
+    pub text: String,
+    /// Chunks received so far by the call, `text` included
+    pub chunks: u32,
+}
+
+/// Receives the progress of the model calls made inside [`ProgressSink::scope`]
+#[derive(Debug, Clone)]
+pub struct ProgressSink(UnboundedSender<Progress>);
+
+impl ProgressSink {
+    pub fn new(sender: UnboundedSender<Progress>) -> Self {
+        Self(sender)
+    }
+
+    /// Runs `future` with this sink as the current one
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT_SINK.scope(self.clone(), future).await
+    }
+}
+
+/// Sends a chunk to the current sink, if any. A receiver that went away is
+/// not an error, the call just completes without anyone watching.
+pub fn report(text: &str, chunks: u32) {
+    let _ = CURRENT_SINK.try_with(|sink| {
+        let _ = sink.0.send(Progress {
+            step: trace::current_step(),
+            text: text.to_string(),
+            chunks,
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::providers::stream::{collect, StreamEvent, TokenStream};
+    use futures::stream;
+
+    #[tokio::test]
+    async fn test_collect_reports_chunks_to_the_current_sink() {
+        let tokens = || -> TokenStream {
+            Box::pin(stream::iter(vec![
+                Ok(StreamEvent::Text("{\"a\":".to_string())),
+                Ok(StreamEvent::Text(" 1}".to_string())),
+            ]))
+        };
+
+        // Without a sink the chunks are only collected
+        assert_eq!(collect(tokens()).await.unwrap().text, "{\"a\": 1}");
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let trace = trace::ExecutionTrace::default();
+        trace.enter_step(Some("json_generation"));
+        let text = trace
+            .scope(ProgressSink::new(sender).scope(collect(tokens())))
+            .await
+            .unwrap()
+            .text;
+        assert_eq!(text, "{\"a\": 1}");
+
+        let first = receiver.recv().await.unwrap();
+        assert_eq!(first.step.as_deref(), Some("json_generation"));
+        assert_eq!((first.text.as_str(), first.chunks), ("{\"a\":", 1));
+        assert_eq!(receiver.recv().await.unwrap().chunks, 2);
+        assert!(receiver.recv().await.is_none());
+    }
+}
@@ -0,0 +1,188 @@
+// src/workflow/trace.rs - What happened while a workflow ran, returned with the analysis result
+
+use super::cascade::EscalationReason;
+use crate::json_repair::Repair;
+use crate::models::providers::truncation::TruncationStrategy;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static CURRENT_TRACE: ExecutionTrace;
+}
+
+/// Why a provider of a failover chain was skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverReason {
+    ConnectionRefused,
+    Timeout,
+    RateLimited,
+    ServerError,
+    EmptyResponse,
+}
+
+/// Which request of a hedged call answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HedgeWinner {
+    Primary,
+    Hedge,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEvent {
+    StepCompleted {
+        duration_ms: u64,
+    },
+    StepFailed {
+        duration_ms: u64,
+        error: String,
+    },
+    /// A provider of a failover chain failed and the next one was tried
+    ProviderFailed {
+        provider: String,
+        model: String,
+        reason: FailoverReason,
+        error: String,
+    },
+    /// The provider of a failover chain that produced the response
+    ProviderAnswered {
+        provider: String,
+        model: String,
+    },
+    /// A model call was looked up in the response cache
+    CacheLookup {
+        provider: String,
+        model: String,
+        hit: bool,
+    },
+    /// A provider rejected a call with 429; every call to it waits `retry_after_ms` before it is sent again
+    RateLimited {
+        provider: String,
+        retry_after_ms: u64,
+    },
+    /// A slow call was duplicated after `delay_ms`
+    Hedged {
+        delay_ms: u64,
+        winner: HedgeWinner,
+    },
+    /// A cascading step handed over to its stronger model
+    Escalated {
+        from_model: String,
+        to_model: String,
+        reason: EscalationReason,
+    },
+    /// Tokens consumed by a model call, `estimated` when the provider didn't report them all
+    TokenUsage {
+        model: String,
+        input_tokens: u32,
+        output_tokens: u32,
+        estimated: bool,
+    },
+    /// A response was cut at `max_tokens`, `strategy` says what was done about it
+    Truncated {
+        model: String,
+        max_tokens: u32,
+        strategy: TruncationStrategy,
+    },
+    /// Tool calling didn't select an endpoint and the prompt path was used instead
+    ToolCallFallback {
+        reason: String,
+    },
+    /// Reasoning (`<think>` blocks, ...) stripped from a response before parsing it
+    Reasoning {
+        text: String,
+    },
+    /// A response only gave a JSON object once `repairs` were applied to it
+    JsonRepaired {
+        repairs: Vec<Repair>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEntry {
+    /// Step that was running, `None` outside of steps
+    pub step: Option<String>,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    step: Option<String>,
+    entries: Vec<TraceEntry>,
+}
+
+/// Execution trace of one workflow run. Code running inside [`ExecutionTrace::scope`],
+/// providers included, appends to it through [`record`] without having it passed along.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionTrace(Arc<Mutex<TraceState>>);
+
+impl ExecutionTrace {
+    /// Runs `future` with this trace as the current one
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT_TRACE.scope(self.clone(), future).await
+    }
+
+    /// Sets the step that subsequent events are attributed to
+    pub fn enter_step(&self, step: Option<&str>) {
+        self.0.lock().unwrap().step = step.map(str::to_string);
+    }
+
+    pub fn push(&self, event: TraceEvent) {
+        let mut state = self.0.lock().unwrap();
+        let step = state.step.clone();
+        state.entries.push(TraceEntry { step, event });
+    }
+
+    pub fn entries(&self) -> Vec<TraceEntry> {
+        self.0.lock().unwrap().entries.clone()
+    }
+}
+
+/// Appends an event to the current trace, if any
+pub fn record(event: TraceEvent) {
+    let _ = CURRENT_TRACE.try_with(|trace| trace.push(event));
+}
+
+/// Step the current trace attributes events to, if any
+pub fn current_step() -> Option<String> {
+    CURRENT_TRACE
+        .try_with(|trace| trace.0.lock().unwrap().step.clone())
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_inside_and_outside_scope() {
+        // Outside of a scope, events are silently discarded
+        record(TraceEvent::StepCompleted { duration_ms: 1 });
+
+        let trace = ExecutionTrace::default();
+        trace
+            .scope(async {
+                record(TraceEvent::ProviderAnswered {
+                    provider: "ollama".to_string(),
+                    model: "llama2".to_string(),
+                });
+            })
+            .await;
+        trace.enter_step(Some("json_generation"));
+        trace.push(TraceEvent::StepCompleted { duration_ms: 5 });
+
+        let entries = trace.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].step, None);
+        assert_eq!(entries[1].step.as_deref(), Some("json_generation"));
+        assert_eq!(
+            serde_json::to_value(&entries[1]).unwrap(),
+            serde_json::json!({"step": "json_generation", "kind": "step_completed", "duration_ms": 5})
+        );
+    }
+}